@@ -0,0 +1,60 @@
+//! Benchmark dell'esecuzione di input generati, da lanciare con `cargo bench`:
+//! per ogni carico stampa il tempo minimo e mediano di `run` su più ripetizioni
+
+use std::fs;
+use std::time::{Duration, Instant};
+
+use piastrelle::generatore::{genera, Profilo};
+use piastrelle::{run, Traccia};
+
+/// Carichi misurati: nome, profilo, seme e scala del generatore
+const CARICHI: [(&str, Profilo, u64, u32); 4] = [
+    // 500 000 piastrelle su un piano molto grande
+    ("sparso_500k", Profilo::Sparso, 1, 1000),
+    ("denso", Profilo::Denso, 1, 4),
+    ("regole", Profilo::Regole, 1, 2),
+    // piste su un piano denso di 160 000 piastrelle
+    ("piste", Profilo::Piste, 1, 10),
+];
+
+/// Ripetizioni di ogni carico
+const RIPETIZIONI: usize = 5;
+
+fn main() {
+    // `cargo bench -- nome` misura solo i carichi il cui nome contiene `nome`
+    let filtro: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|argomento| !argomento.starts_with("--"))
+        .collect();
+    let cartella = std::env::temp_dir();
+
+    for (nome, profilo, seme, scala) in CARICHI {
+        if !filtro.is_empty() && !filtro.iter().any(|f| nome.contains(f.as_str())) {
+            continue;
+        }
+        let input = cartella.join(format!("piastrelle_bench_{}_{nome}.in", std::process::id()));
+        let output = input.with_extension("out");
+        fs::write(&input, genera(profilo, seme, scala)).expect("errore scrivendo l'input");
+
+        let mut tempi: Vec<Duration> = (0..RIPETIZIONI)
+            .map(|_| {
+                let inizio = Instant::now();
+                run(
+                    Some(input.display().to_string()),
+                    Some(output.display().to_string()),
+                    Traccia::Nessuna,
+                );
+                inizio.elapsed()
+            })
+            .collect();
+        tempi.sort();
+        println!(
+            "{nome:<12} minimo {:>10.2?}  mediano {:>10.2?}",
+            tempi[0],
+            tempi[RIPETIZIONI / 2]
+        );
+
+        let _ = fs::remove_file(&input);
+        let _ = fs::remove_file(&output);
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+use crate::{Piastrella, Tinta};
+
+/// Archivio delle piastrelle accese di un piano, con la tinta di ciascuna. È letto
+/// da più thread insieme durante le operazioni parallele del piano
+pub trait Archivio: Default + Sync {
+    /// Restituisce la tinta della piastrella, se accesa
+    fn get(&self, piastrella: &Piastrella) -> Option<&Tinta>;
+
+    /// Accende la piastrella con la tinta `tinta`, restituendo la tinta precedente
+    fn insert(&mut self, piastrella: Piastrella, tinta: Tinta) -> Option<Tinta>;
+
+    /// Spegne la piastrella, restituendo la sua tinta se era accesa
+    fn remove(&mut self, piastrella: &Piastrella) -> Option<Tinta>;
+
+    /// Restituisce il numero di piastrelle accese
+    fn len(&self) -> usize;
+
+    /// Restituisce le piastrelle accese con la loro tinta, in un ordine qualsiasi
+    fn iter(&self) -> impl Iterator<Item = (Piastrella, &Tinta)>;
+
+    /// Spegne le piastrelle per cui `tenere` è falso
+    fn retain(&mut self, tenere: impl FnMut(&Piastrella, &Tinta) -> bool);
+}
+
+/// Archivio con una tabella hash di piastrelle: adatto a qualsiasi piano, ma ogni
+/// accesso calcola l'hash della piastrella
+impl Archivio for HashMap<Piastrella, Tinta> {
+    fn get(&self, piastrella: &Piastrella) -> Option<&Tinta> {
+        HashMap::get(self, piastrella)
+    }
+
+    fn insert(&mut self, piastrella: Piastrella, tinta: Tinta) -> Option<Tinta> {
+        HashMap::insert(self, piastrella, tinta)
+    }
+
+    fn remove(&mut self, piastrella: &Piastrella) -> Option<Tinta> {
+        HashMap::remove(self, piastrella)
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Piastrella, &Tinta)> {
+        HashMap::iter(self).map(|(piastrella, tinta)| (piastrella.clone(), tinta))
+    }
+
+    fn retain(&mut self, mut tenere: impl FnMut(&Piastrella, &Tinta) -> bool) {
+        HashMap::retain(self, |piastrella, tinta| tenere(piastrella, tinta))
+    }
+}
+
+/// Lato di un blocco di `Blocchi`, in piastrelle (potenza di 2)
+const LATO: i32 = 64;
+/// Piastrelle di un blocco di `Blocchi`
+const CELLE: usize = (LATO * LATO) as usize;
+/// Un blocco sparso con più piastrelle accese diventa denso
+const MASSIMO_SPARSO: usize = CELLE / 16;
+/// Un blocco denso con meno piastrelle accese torna sparso
+const MINIMO_DENSO: usize = CELLE / 64;
+
+/// Archivio a blocchi di `LATO`x`LATO` piastrelle, indicizzati da una tabella hash.
+/// Un blocco con molte piastrelle accese è un array denso, quindi le piastrelle
+/// adiacenti sono quasi sempre nello stesso blocco, in memoria contigua; un blocco
+/// con poche piastrelle accese è un vettore ordinato, così un piano sparso non occupa
+/// più memoria di una tabella hash
+#[derive(Default)]
+pub struct Blocchi {
+    blocchi: HashMap<(i32, i32), Blocco, BuildHasherDefault<HashBlocchi>>,
+    accese: usize,
+}
+
+/// Blocco di `Blocchi`: le piastrelle sono indicate dalla loro posizione nel blocco,
+/// riga per riga
+enum Blocco {
+    /// piastrelle accese ordinate per posizione
+    Sparso(Vec<(u16, Tinta)>),
+    /// tutte le piastrelle del blocco, con il numero di quelle accese
+    Denso(Box<[Option<Tinta>]>, usize),
+}
+
+impl Blocco {
+    fn len(&self) -> usize {
+        match self {
+            Blocco::Sparso(celle) => celle.len(),
+            Blocco::Denso(_, accese) => *accese,
+        }
+    }
+
+    fn get(&self, posizione: u16) -> Option<&Tinta> {
+        match self {
+            Blocco::Sparso(celle) => celle
+                .binary_search_by_key(&posizione, |(p, _)| *p)
+                .ok()
+                .map(|i| &celle[i].1),
+            Blocco::Denso(celle, _) => celle[posizione as usize].as_ref(),
+        }
+    }
+
+    fn insert(&mut self, posizione: u16, tinta: Tinta) -> Option<Tinta> {
+        let precedente = match self {
+            Blocco::Sparso(celle) => match celle.binary_search_by_key(&posizione, |(p, _)| *p) {
+                Ok(i) => Some(std::mem::replace(&mut celle[i].1, tinta)),
+                Err(i) => {
+                    celle.insert(i, (posizione, tinta));
+                    None
+                }
+            },
+            Blocco::Denso(celle, accese) => {
+                let precedente = celle[posizione as usize].replace(tinta);
+                if precedente.is_none() {
+                    *accese += 1;
+                }
+                precedente
+            }
+        };
+
+        if let Blocco::Sparso(celle) = self {
+            if celle.len() > MASSIMO_SPARSO {
+                let mut dense = vec![None; CELLE].into_boxed_slice();
+                for (posizione, tinta) in celle.iter() {
+                    dense[*posizione as usize] = Some(*tinta);
+                }
+                *self = Blocco::Denso(dense, celle.len());
+            }
+        }
+        precedente
+    }
+
+    fn remove(&mut self, posizione: u16) -> Option<Tinta> {
+        let rimossa = match self {
+            Blocco::Sparso(celle) => celle
+                .binary_search_by_key(&posizione, |(p, _)| *p)
+                .ok()
+                .map(|i| celle.remove(i).1),
+            Blocco::Denso(celle, accese) => {
+                let rimossa = celle[posizione as usize].take();
+                if rimossa.is_some() {
+                    *accese -= 1;
+                }
+                rimossa
+            }
+        };
+
+        if let Blocco::Denso(celle, accese) = self {
+            if *accese < MINIMO_DENSO {
+                let sparse = celle
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(posizione, tinta)| Some((posizione as u16, (*tinta)?)))
+                    .collect();
+                *self = Blocco::Sparso(sparse);
+            }
+        }
+        rimossa
+    }
+
+    /// Le piastrelle accese del blocco, con la loro posizione
+    fn iter(&self) -> Box<dyn Iterator<Item = (u16, &Tinta)> + '_> {
+        match self {
+            Blocco::Sparso(celle) => Box::new(celle.iter().map(|(p, tinta)| (*p, tinta))),
+            Blocco::Denso(celle, _) => Box::new(
+                celle
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(p, tinta)| Some((p as u16, tinta.as_ref()?))),
+            ),
+        }
+    }
+}
+
+/// Restituisce il blocco della piastrella e la sua posizione nel blocco
+fn dividi(Piastrella { x, y }: &Piastrella) -> ((i32, i32), u16) {
+    // lo shift aritmetico arrotonda verso il basso anche le coordinate negative
+    let blocco = (x >> LATO.trailing_zeros(), y >> LATO.trailing_zeros());
+    let posizione = (y & (LATO - 1)) * LATO + (x & (LATO - 1));
+    (blocco, posizione as u16)
+}
+
+/// Restituisce la piastrella in posizione `posizione` del blocco `blocco`
+fn unisci((bx, by): (i32, i32), posizione: u16) -> Piastrella {
+    let posizione = posizione as i32;
+    Piastrella {
+        x: bx * LATO + posizione % LATO,
+        y: by * LATO + posizione / LATO,
+    }
+}
+
+impl Archivio for Blocchi {
+    fn get(&self, piastrella: &Piastrella) -> Option<&Tinta> {
+        let (blocco, posizione) = dividi(piastrella);
+        self.blocchi.get(&blocco)?.get(posizione)
+    }
+
+    fn insert(&mut self, piastrella: Piastrella, tinta: Tinta) -> Option<Tinta> {
+        let (blocco, posizione) = dividi(&piastrella);
+        let precedente = self
+            .blocchi
+            .entry(blocco)
+            .or_insert_with(|| Blocco::Sparso(Vec::new()))
+            .insert(posizione, tinta);
+        if precedente.is_none() {
+            self.accese += 1;
+        }
+        precedente
+    }
+
+    fn remove(&mut self, piastrella: &Piastrella) -> Option<Tinta> {
+        let (blocco, posizione) = dividi(piastrella);
+        let contenitore = self.blocchi.get_mut(&blocco)?;
+        let rimossa = contenitore.remove(posizione)?;
+        if contenitore.len() == 0 {
+            self.blocchi.remove(&blocco);
+        }
+        self.accese -= 1;
+        Some(rimossa)
+    }
+
+    fn len(&self) -> usize {
+        self.accese
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Piastrella, &Tinta)> {
+        self.blocchi.iter().flat_map(|(blocco, contenitore)| {
+            contenitore
+                .iter()
+                .map(|(posizione, tinta)| (unisci(*blocco, posizione), tinta))
+        })
+    }
+
+    fn retain(&mut self, mut tenere: impl FnMut(&Piastrella, &Tinta) -> bool) {
+        let spente: Vec<Piastrella> = self
+            .iter()
+            .filter(|(piastrella, tinta)| !tenere(piastrella, tinta))
+            .map(|(piastrella, _)| piastrella)
+            .collect();
+        for piastrella in spente {
+            self.remove(&piastrella);
+        }
+    }
+}
+
+/// Hash delle coordinate dei blocchi (moltiplicativo, come FxHash): le chiavi sono
+/// coppie di interi scelte dai comandi, non serve la resistenza alle collisioni di SipHash.
+/// Usato anche per le zone dell'indice spaziale
+#[derive(Default)]
+pub struct HashBlocchi(u64);
+
+impl Hasher for HashBlocchi {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(*byte as u64);
+        }
+    }
+
+    fn write_i32(&mut self, valore: i32) {
+        self.write_u64(valore as u32 as u64);
+    }
+
+    fn write_u64(&mut self, valore: u64) {
+        self.0 = (self.0.rotate_left(5) ^ valore).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+}
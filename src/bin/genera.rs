@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use piastrelle::generatore::{genera, Profilo, PROFILI};
+use piastrelle::{run, Traccia};
+
+const USO: &str = "uso: genera profilo [--seme n] [--scala n] [--output file.in [--atteso]]";
+
+/// Termina con un errore di utilizzo
+fn errore(messaggio: &str) -> ! {
+    let profili: Vec<&str> = PROFILI.iter().map(|(nome, _)| *nome).collect();
+    eprintln!("{messaggio}\n{USO}\nprofili: {}", profili.join(", "));
+    process::exit(2);
+}
+
+/// Genera i comandi di un profilo sullo standard output o nel file indicato da `--output`,
+/// scrivendo con `--atteso` anche l'output atteso (`file.out`) prodotto dal piano
+fn main() {
+    let mut argomenti = std::env::args().skip(1);
+    let mut profilo: Option<Profilo> = None;
+    let mut seme: u64 = 0;
+    let mut scala: u32 = 1;
+    let mut output: Option<String> = None;
+    let mut atteso = false;
+
+    while let Some(argomento) = argomenti.next() {
+        let mut valore = |nome: &str| {
+            argomenti
+                .next()
+                .unwrap_or_else(|| errore(&format!("valore mancante per {nome}")))
+        };
+        match argomento.as_str() {
+            "--seme" => {
+                seme = valore("--seme")
+                    .parse()
+                    .unwrap_or_else(|_| errore("seme non valido"))
+            }
+            "--scala" => {
+                scala = valore("--scala")
+                    .parse()
+                    .unwrap_or_else(|_| errore("scala non valida"))
+            }
+            "--output" => output = Some(valore("--output")),
+            "--atteso" => atteso = true,
+            nome if profilo.is_none() => {
+                profilo = Some(
+                    Profilo::cerca(nome)
+                        .unwrap_or_else(|| errore(&format!("profilo sconosciuto {nome}"))),
+                )
+            }
+            _ => errore(&format!("argomento sconosciuto {argomento}")),
+        }
+    }
+
+    let profilo = profilo.unwrap_or_else(|| errore("profilo mancante"));
+    // la scala massima dipende dal profilo, che può seguire `--scala`
+    if !(1..=profilo.scala_massima()).contains(&scala) {
+        let massima = profilo.scala_massima();
+        errore(&format!("scala non valida (da 1 a {massima})"));
+    }
+    let comandi = genera(profilo, seme, scala);
+    match output {
+        Some(input) => {
+            if fs::write(&input, comandi).is_err() {
+                errore(&format!("errore scrivendo il file {input}"));
+            }
+            if atteso {
+                let output = Path::new(&input).with_extension("out");
+                run(
+                    Some(input),
+                    Some(output.display().to_string()),
+                    Traccia::Nessuna,
+                );
+            }
+        }
+        None if atteso => errore("--atteso richiede --output"),
+        None => print!("{comandi}"),
+    }
+}
@@ -0,0 +1,103 @@
+use std::fs;
+use std::panic;
+use std::path::Path;
+use std::process;
+
+use piastrelle::minimizzatore::{minimizza, Criterio};
+
+const USO: &str =
+    "uso: minimizza input.in (--panico | --crash | --atteso file.out) [--output file.in]";
+
+/// Termina con un errore di utilizzo
+fn errore(messaggio: &str) -> ! {
+    eprintln!("{messaggio}\n{USO}");
+    process::exit(2);
+}
+
+/// Riduce i comandi di un input ad una sequenza minimale che fallisce ancora secondo il
+/// criterio indicato, scrivendola sullo standard output o nel file indicato da `--output`
+fn main() {
+    let mut argomenti = std::env::args().skip(1);
+    let mut input: Option<String> = None;
+    let mut criterio: Option<String> = None;
+    let mut atteso: Option<String> = None;
+    let mut output: Option<String> = None;
+
+    while let Some(argomento) = argomenti.next() {
+        let mut valore = |nome: &str| {
+            argomenti
+                .next()
+                .unwrap_or_else(|| errore(&format!("valore mancante per {nome}")))
+        };
+        match argomento.as_str() {
+            "--panico" | "--crash" | "--atteso" if criterio.is_some() => {
+                errore("criterio già indicato")
+            }
+            "--panico" | "--crash" => criterio = Some(argomento),
+            "--atteso" => {
+                atteso = Some(valore("--atteso"));
+                criterio = Some(argomento);
+            }
+            "--output" => output = Some(valore("--output")),
+            _ if input.is_none() && !argomento.starts_with("--") => input = Some(argomento),
+            _ => errore(&format!("argomento sconosciuto {argomento}")),
+        }
+    }
+
+    let input = input.unwrap_or_else(|| errore("input mancante"));
+    let righe: Vec<String> = match fs::read_to_string(&input) {
+        Ok(contenuto) => contenuto.lines().map(String::from).collect(),
+        Err(..) => errore(&format!("errore aprendo il file {input}")),
+    };
+    // i file temporanei sono accanto all'input, così le inclusioni restano valide
+    let cartella = Path::new(&input)
+        .parent()
+        .filter(|cartella| !cartella.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+
+    let criterio = match criterio.as_deref() {
+        Some("--panico") => Criterio::Panico,
+        Some("--crash") => match std::env::current_exe() {
+            Ok(programma) => Criterio::Crash(programma.with_file_name("piastrelle")),
+            Err(..) => errore("programma piastrelle non trovato"),
+        },
+        Some(_) => {
+            let atteso = atteso.unwrap();
+            let atteso = fs::read_to_string(&atteso)
+                .unwrap_or_else(|_| errore(&format!("errore aprendo il file {atteso}")));
+            // la prima esecuzione può andare in panic, il messaggio è nell'errore
+            panic::set_hook(Box::new(|_| ()));
+            Criterio::risposta(&righe, &atteso, &cartella).unwrap_or_else(|e| errore(&e))
+        }
+        None => errore("criterio mancante"),
+    };
+    // i panic dei candidati sono attesi, non vanno stampati
+    panic::set_hook(Box::new(|_| ()));
+
+    let righe: Vec<(usize, String)> = righe.into_iter().enumerate().collect();
+    if !criterio.fallisce(&righe, &cartella) {
+        errore("l'input completo non fallisce secondo il criterio");
+    }
+
+    let mut esecuzioni = 0;
+    let minimo = minimizza(righe.clone(), |candidato| {
+        esecuzioni += 1;
+        criterio.fallisce(candidato, &cartella)
+    });
+    eprintln!(
+        "da {} a {} righe in {esecuzioni} esecuzioni",
+        righe.len(),
+        minimo.len()
+    );
+
+    let minimo: String = minimo.iter().map(|(_, riga)| format!("{riga}\n")).collect();
+    match output {
+        Some(output) => {
+            if fs::write(&output, minimo).is_err() {
+                errore(&format!("errore scrivendo il file {output}"));
+            }
+        }
+        None => print!("{minimo}"),
+    }
+}
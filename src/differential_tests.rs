@@ -0,0 +1,343 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::generatore::{genera, Profilo};
+use crate::*;
+
+/// Regola del riferimento: colore finale, requisiti (coefficiente e colore) e utilizzo
+type RegolaRiferimento = (String, Vec<(u8, String)>, u32);
+
+/// Implementazione di riferimento del piano, volutamente ingenua: i blocchi sono calcolati
+/// scandendo tutte le piastrelle finché il blocco non cresce più, `lung` con Bellman-Ford
+/// e le regole valutate contando ogni volta l'intorno
+struct Riferimento {
+    piastrelle: BTreeMap<(i32, i32), (String, u32)>,
+    regole: Vec<RegolaRiferimento>,
+}
+
+/// Le 8 posizioni adiacenti ad una piastrella
+fn intorno((x, y): (i32, i32)) -> impl Iterator<Item = (i32, i32)> {
+    (-1..=1)
+        .flat_map(move |dx| (-1..=1).map(move |dy| (x + dx, y + dy)))
+        .filter(move |adiacente| *adiacente != (x, y))
+}
+
+impl Riferimento {
+    fn new() -> Self {
+        Self {
+            piastrelle: BTreeMap::new(),
+            regole: Vec::new(),
+        }
+    }
+
+    /// Piastrelle del blocco (omogeneo se `omogeneo`) di `inizio`: ogni scansione di tutte
+    /// le piastrelle aggiunge quelle adiacenti ad una del blocco, finché il blocco non cresce
+    fn blocco(&self, inizio: (i32, i32), omogeneo: bool) -> BTreeSet<(i32, i32)> {
+        let Some((colore, _)) = self.piastrelle.get(&inizio) else {
+            return BTreeSet::new();
+        };
+        let mut blocco = BTreeSet::from([inizio]);
+        loop {
+            let mut cresciuto = false;
+            for (piastrella, (c, _)) in &self.piastrelle {
+                if !blocco.contains(piastrella)
+                    && (!omogeneo || c == colore)
+                    && intorno(*piastrella).any(|adiacente| blocco.contains(&adiacente))
+                {
+                    blocco.insert(*piastrella);
+                    cresciuto = true;
+                }
+            }
+            if !cresciuto {
+                return blocco;
+            }
+        }
+    }
+
+    fn intensita_blocco(&self, inizio: (i32, i32), omogeneo: bool) -> u32 {
+        self.blocco(inizio, omogeneo)
+            .iter()
+            .map(|piastrella| self.piastrelle[piastrella].1)
+            .sum()
+    }
+
+    /// Indice e colore della prima regola i cui requisiti sono soddisfatti dalle
+    /// piastrelle accese adiacenti a `piastrella`
+    fn regola(&self, piastrella: (i32, i32)) -> Option<(usize, String)> {
+        self.regole
+            .iter()
+            .enumerate()
+            .find(|(_, (_, requisiti, _))| {
+                requisiti.iter().all(|(coefficiente, colore)| {
+                    let presenti = intorno(piastrella)
+                        .filter(|adiacente| {
+                            self.piastrelle
+                                .get(adiacente)
+                                .is_some_and(|(c, _)| c == colore)
+                        })
+                        .count();
+                    presenti >= *coefficiente as usize
+                })
+            })
+            .map(|(i, (colore, ..))| (i, colore.clone()))
+    }
+
+    fn propaga(&mut self, piastrella: (i32, i32)) {
+        if let Some((i, colore)) = self.regola(piastrella) {
+            let intensita = self.piastrelle.get(&piastrella).map_or(1, |(_, i)| *i);
+            self.piastrelle.insert(piastrella, (colore, intensita));
+            self.regole[i].2 += 1;
+        }
+    }
+
+    fn propaga_blocco(&mut self, inizio: (i32, i32)) {
+        let applicazioni: Vec<((i32, i32), usize, String)> = self
+            .blocco(inizio, false)
+            .into_iter()
+            .filter_map(|piastrella| {
+                let (i, colore) = self.regola(piastrella)?;
+                Some((piastrella, i, colore))
+            })
+            .collect();
+        for (piastrella, i, colore) in applicazioni {
+            self.piastrelle.get_mut(&piastrella).unwrap().0 = colore;
+            self.regole[i].2 += 1;
+        }
+    }
+
+    fn pista(&self, (mut x, mut y): (i32, i32), direzioni: &[&str]) -> Option<u32> {
+        let mut totale = self.piastrelle.get(&(x, y))?.1;
+        for direzione in direzioni {
+            let (dx, dy) = match *direzione {
+                "NN" => (0, 1),
+                "SS" => (0, -1),
+                "EE" => (1, 0),
+                "WW" => (-1, 0),
+                "NE" => (1, 1),
+                "NW" => (-1, 1),
+                "SE" => (1, -1),
+                "SW" => (-1, -1),
+                _ => return None,
+            };
+            (x, y) = (x + dx, y + dy);
+            totale += self.piastrelle.get(&(x, y))?.1;
+        }
+        Some(totale)
+    }
+
+    /// Lunghezza della pista più breve con Bellman-Ford su una griglia densa: ogni giro
+    /// rilassa gli archi verso ogni piastrella accesa (in avanti e all'indietro a giri
+    /// alterni), fino a quando nessuna distanza cambia
+    fn lung(&self, inizio: (i32, i32), fine: (i32, i32)) -> Option<u32> {
+        let intensita_inizio = self.piastrelle.get(&inizio)?.1;
+
+        // rettangolo che contiene tutte le piastrelle accese
+        let x0 = self.piastrelle.keys().map(|(x, _)| *x).min()?;
+        let x1 = self.piastrelle.keys().map(|(x, _)| *x).max()?;
+        let y0 = self.piastrelle.keys().map(|(_, y)| *y).min()?;
+        let y1 = self.piastrelle.keys().map(|(_, y)| *y).max()?;
+        let larghezza = (x1 - x0 + 1) as usize;
+        let celle = larghezza * (y1 - y0 + 1) as usize;
+        let indice = |(x, y): (i32, i32)| {
+            ((x0..=x1).contains(&x) && (y0..=y1).contains(&y))
+                .then(|| (y - y0) as usize * larghezza + (x - x0) as usize)
+        };
+
+        let mut intensita = vec![None; celle];
+        for (piastrella, (_, i)) in &self.piastrelle {
+            intensita[indice(*piastrella).unwrap()] = Some(*i);
+        }
+        let mut distanze = vec![u32::MAX; celle];
+        distanze[indice(inizio).unwrap()] = intensita_inizio;
+
+        for giro in 0.. {
+            let mut cambiate = false;
+            for cella in 0..celle {
+                let cella = if giro % 2 == 0 {
+                    cella
+                } else {
+                    celle - 1 - cella
+                };
+                let Some(i) = intensita[cella] else {
+                    continue;
+                };
+                let piastrella = (
+                    x0 + (cella % larghezza) as i32,
+                    y0 + (cella / larghezza) as i32,
+                );
+                let vicina = intorno(piastrella)
+                    .filter_map(indice)
+                    .map(|adiacente| distanze[adiacente])
+                    .filter(|d| *d != u32::MAX)
+                    .min();
+                if let Some(vicina) = vicina.filter(|d| d + i < distanze[cella]) {
+                    distanze[cella] = vicina + i;
+                    cambiate = true;
+                }
+            }
+            if !cambiate {
+                break;
+            }
+        }
+        indice(fine)
+            .map(|cella| distanze[cella])
+            .filter(|d| *d != u32::MAX)
+    }
+
+    /// Esegue un comando del sottoinsieme usato dal generatore
+    ///
+    /// # Returns
+    /// * le righe di output del comando
+    fn esegui(&mut self, riga: &str) -> Vec<String> {
+        let parti: Vec<&str> = riga.split_whitespace().collect();
+        let numero = |i: usize| parti[i].parse::<i32>().unwrap();
+        let mut output = Vec::new();
+        match parti[0] {
+            "C" => {
+                let colorazione = (String::from(parti[3]), parti[4].parse().unwrap());
+                self.piastrelle.insert((numero(1), numero(2)), colorazione);
+            }
+            "F" => {
+                for x in numero(1).min(numero(3))..=numero(1).max(numero(3)) {
+                    for y in numero(2).min(numero(4))..=numero(2).max(numero(4)) {
+                        let colorazione = (String::from(parti[5]), parti[6].parse().unwrap());
+                        self.piastrelle.insert((x, y), colorazione);
+                    }
+                }
+            }
+            "S" => {
+                self.piastrelle.remove(&(numero(1), numero(2)));
+            }
+            "?" => {
+                if let Some((colore, intensita)) = self.piastrelle.get(&(numero(1), numero(2))) {
+                    output.push(format!("{colore} {intensita}"));
+                }
+            }
+            "r" => {
+                let requisiti = parti[2..]
+                    .chunks(2)
+                    .map(|r| (r[0].parse().unwrap(), String::from(r[1])))
+                    .collect();
+                self.regole.push((String::from(parti[1]), requisiti, 0));
+            }
+            "s" => {
+                output.push(String::from("("));
+                for (colore, requisiti, _) in &self.regole {
+                    let requisiti: String =
+                        requisiti.iter().map(|(k, c)| format!(" {k} {c}")).collect();
+                    output.push(format!("{colore}:{requisiti}"));
+                }
+                output.push(String::from(")"));
+            }
+            "b" => output.push(
+                self.intensita_blocco((numero(1), numero(2)), false)
+                    .to_string(),
+            ),
+            "B" => output.push(
+                self.intensita_blocco((numero(1), numero(2)), true)
+                    .to_string(),
+            ),
+            "p" => self.propaga((numero(1), numero(2))),
+            "P" => self.propaga_blocco((numero(1), numero(2))),
+            "o" => self.regole.sort_by_key(|(_, _, utilizzo)| *utilizzo),
+            "t" => output.extend(
+                self.pista((numero(1), numero(2)), &parti[3..])
+                    .map(|i| i.to_string()),
+            ),
+            "L" => output.extend(
+                self.lung((numero(1), numero(2)), (numero(3), numero(4)))
+                    .map(|l| l.to_string()),
+            ),
+            "q" => (),
+            comando => panic!("comando {comando} non supportato dal riferimento"),
+        }
+        output
+    }
+}
+
+/// Esegue i comandi sia sul piano che sul riferimento, fallendo al primo comando il cui
+/// output o il cui effetto sulle piastrelle è diverso. Solo una richiesta `L` ogni 8 è
+/// confrontata, dato il costo di Bellman-Ford
+fn confronta(nome: &str, comandi: &str) {
+    let mut piano = Piano::new();
+    let mut riferimento = Riferimento::new();
+
+    for (numero, riga) in comandi.lines().enumerate() {
+        let mut ottenuto = Vec::new();
+        esegui(&mut piano, riga, Formato::Testo, &mut |s| {
+            ottenuto.extend(s.lines().map(String::from))
+        })
+        .unwrap();
+        let parti: Vec<&str> = riga.split_whitespace().collect();
+        if parti[0] == "L" && numero % 8 != 0 {
+            continue;
+        }
+        let atteso = riferimento.esegui(riga);
+        assert_eq!(
+            ottenuto,
+            atteso,
+            "{nome}: output diverso alla riga {} ({riga})",
+            numero + 1
+        );
+
+        match parti[0] {
+            // una sola piastrella modificata
+            "C" | "S" => {
+                let (x, y) = (parti[1].parse().unwrap(), parti[2].parse().unwrap());
+                let ottenuta = piano.stato(x, y).map(|c| (c.colore, c.intensita));
+                assert_eq!(
+                    ottenuta.as_ref(),
+                    riferimento.piastrelle.get(&(x, y)),
+                    "{nome}: piastrella diversa dopo la riga {} ({riga})",
+                    numero + 1
+                );
+            }
+            // qualsiasi piastrella modificata
+            "F" | "p" | "P" => {
+                let piastrelle: BTreeMap<(i32, i32), (String, u32)> = piano
+                    .colorazioni()
+                    .into_iter()
+                    .map(|(p, c)| ((p.x, p.y), (c.colore, c.intensita)))
+                    .collect();
+                assert!(
+                    piastrelle == riferimento.piastrelle,
+                    "{nome}: piastrelle diverse dopo la riga {} ({riga})",
+                    numero + 1
+                );
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Confronta piano e riferimento sui comandi generati con il profilo `nome`, per ogni seme
+fn differenziale(nome: &str, semi: std::ops::Range<u64>) {
+    let profilo = Profilo::cerca(nome).unwrap();
+    for seme in semi {
+        confronta(&format!("{nome} seme {seme}"), &genera(profilo, seme, 1));
+    }
+}
+
+#[test]
+fn test_differenziale_denso() {
+    differenziale("denso", 0..4);
+}
+
+#[test]
+fn test_differenziale_sparso() {
+    differenziale("sparso", 0..4);
+}
+
+#[test]
+fn test_differenziale_regole() {
+    differenziale("regole", 0..1);
+}
+
+#[test]
+fn test_differenziale_piste() {
+    differenziale("piste", 0..4);
+}
+
+#[test]
+fn test_differenziale_percorsi() {
+    differenziale("percorsi", 0..1);
+}
@@ -0,0 +1,720 @@
+use std::collections::HashMap;
+
+use crate::{Archivio, Colorazione, Piano, Piastrella, Regola, Tinta};
+
+/// Piastrella da colorare durante un'importazione: ascisse, ordinate, colore e intensità
+type Importata = (i32, i32, String, u32);
+
+impl<A: Archivio> Piano<A> {
+    /// Importa una griglia di caratteri, colorando le piastrelle come una sequenza di
+    /// `colora`. La griglia è preceduta da una legenda, terminata da una riga vuota,
+    /// con una riga `carattere colore intensita` per ogni carattere usato:
+    /// ```format
+    /// r rosso 1
+    /// g verde 3
+    ///
+    /// rr.g
+    /// r..g
+    /// ```
+    /// La prima riga della griglia è la più a nord: il carattere in colonna `i` della
+    /// riga `j` (contando dal basso, da 0) corrisponde alla piastrella (`x + i`, `y + j`).
+    /// I caratteri `.` e spazio indicano piastrelle da lasciare invariate
+    ///
+    /// # Arguments
+    /// * `griglia` - testo della legenda e della griglia
+    /// * `x`, `y` - posizione dell'angolo in basso a sinistra della griglia
+    ///
+    /// # Returns
+    /// * `Err(String)` - la descrizione dell'errore se la legenda o la griglia sono
+    ///   malformate o escono dal piano, in tal caso il piano **non** è modificato
+    pub(crate) fn importa_griglia(&mut self, griglia: &str, x: i32, y: i32) -> Result<(), String> {
+        let piastrelle = parsa_griglia(griglia, x, y)?;
+        self._importa(piastrelle);
+        Ok(())
+    }
+
+    /// Importa un'immagine PPM (formato `P3` testuale o `P6` binario), colorando una
+    /// piastrella per pixel come una sequenza di `colora`. Il colore di ogni piastrella è
+    /// il colore del pixel in esadecimale (`rrggbb`, scalato a 255), i pixel neri
+    /// (`000000`) indicano piastrelle da lasciare invariate. La prima riga
+    /// dell'immagine è la più a nord, come in `importa_griglia`
+    ///
+    /// # Arguments
+    /// * `immagine` - contenuto del file PPM
+    /// * `x`, `y` - posizione dell'angolo in basso a sinistra dell'immagine
+    /// * `intensita` - intensità di tutte le piastrelle
+    ///
+    /// # Returns
+    /// * `Err(String)` - la descrizione dell'errore se l'immagine è malformata o esce
+    ///   dal piano, in tal caso il piano **non** è modificato
+    pub(crate) fn importa_ppm(
+        &mut self,
+        immagine: &[u8],
+        x: i32,
+        y: i32,
+        intensita: u32,
+    ) -> Result<(), String> {
+        if intensita == 0 {
+            return Err(String::from("intensità non valida"));
+        }
+        let piastrelle = parsa_ppm(immagine, x, y, intensita)?;
+        self._importa(piastrelle);
+        Ok(())
+    }
+
+    /// Esporta le piastrelle accese in formato CSV, con intestazione e una riga
+    /// `x,y,colore,intensita` per piastrella, ordinate per ascisse e poi per ordinate:
+    /// ```format
+    /// x,y,colore,intensita
+    /// 0,0,rosso,1
+    /// 0,1,verde,3
+    /// ```
+    /// I colori contenenti virgole o virgolette sono racchiusi tra virgolette
+    ///
+    /// # Returns
+    /// * `String` che rappresenta le piastrelle nel formato descritto
+    pub(crate) fn esporta_csv(&self) -> String {
+        let mut piastrelle: Vec<_> = self.piastrelle.iter().collect();
+        piastrelle.sort_by_key(|(piastrella, _)| (piastrella.x, piastrella.y));
+
+        let mut csv = String::from(INTESTAZIONE_CSV);
+        csv.push('\n');
+        for (Piastrella { x, y }, Tinta { colore, intensita }) in piastrelle {
+            let colore = self.tavolozza.nome(*colore);
+            let colore = if colore.contains([',', '"']) {
+                format!("\"{}\"", colore.replace('"', "\"\""))
+            } else {
+                String::from(colore)
+            };
+            csv.push_str(&format!("{x},{y},{colore},{intensita}\n"));
+        }
+        csv
+    }
+
+    /// Importa delle piastrelle in formato CSV (vedi `esporta_csv`), colorandole come una
+    /// sequenza di `colora`. L'intestazione e le righe vuote sono facoltative
+    ///
+    /// # Arguments
+    /// * `csv` - testo delle piastrelle
+    ///
+    /// # Returns
+    /// * `Err(String)` - la descrizione dell'errore se una riga è malformata,
+    ///   in tal caso il piano **non** è modificato
+    pub(crate) fn importa_csv(&mut self, csv: &str) -> Result<(), String> {
+        let piastrelle = parsa_csv(csv)?;
+        self._importa(piastrelle);
+        Ok(())
+    }
+
+    /// Esporta le regole in formato JSON, come array di oggetti nell'ordine delle regole:
+    /// ```format
+    /// [{"colore":"z","requisiti":[{"coefficiente":1,"colore":"g"}],"utilizzo":2}]
+    /// ```
+    ///
+    /// # Returns
+    /// * `String` che rappresenta le regole nel formato descritto
+    pub(crate) fn esporta_regole(&self) -> String {
+        self.tavolozza.nomina(self.regole.as_slice()).json()
+    }
+
+    /// Importa delle regole in formato JSON (vedi `esporta_regole`), aggiungendole in coda
+    /// alle regole del piano con il loro utilizzo. L'utilizzo è facoltativo (0 se assente)
+    ///
+    /// # Arguments
+    /// * `json` - testo delle regole
+    ///
+    /// # Returns
+    /// * `Err(String)` - la descrizione dell'errore se il JSON o una regola sono
+    ///   malformati, in tal caso il piano **non** è modificato
+    pub(crate) fn importa_regole(&mut self, json: &str) -> Result<(), String> {
+        let regole = self._parsa_regole(json)?;
+        self.regole.extend(regole);
+        self._invalida_indice();
+        Ok(())
+    }
+
+    /// Colora le piastrelle importate, nell'ordine
+    fn _importa(&mut self, piastrelle: Vec<Importata>) {
+        for (x, y, colore, intensita) in piastrelle {
+            self.colora(x, y, colore, intensita);
+        }
+    }
+
+    /// Parsa delle regole in formato JSON (vedi `Piano::importa_regole`), aggiungendo
+    /// i loro colori alla tavolozza solo se sono tutte valide
+    fn _parsa_regole(&mut self, json: &str) -> Result<Vec<Regola>, String> {
+        let Valore::Array(elementi) = Valore::parsa(json)? else {
+            return Err(String::from("regole invalide (array atteso)"));
+        };
+
+        let mut regole = Vec::new();
+        for (i, elemento) in elementi.iter().enumerate() {
+            let errore = |dettaglio: &str| format!("regole invalide (regola {i}: {dettaglio})");
+            // un colore deve essere un singolo argomento di un comando
+            let colore = |valore: Option<&Valore>| match valore {
+                Some(Valore::Stringa(colore))
+                    if !colore.is_empty() && !colore.contains(char::is_whitespace) =>
+                {
+                    Ok(colore.clone())
+                }
+                _ => Err(errore("colore invalido")),
+            };
+
+            let mut testo = colore(elemento.campo("colore"))?;
+            let Some(Valore::Array(requisiti)) = elemento.campo("requisiti") else {
+                return Err(errore("requisiti mancanti"));
+            };
+            for requisito in requisiti {
+                let coefficiente = match requisito.campo("coefficiente") {
+                    Some(Valore::Numero(coefficiente)) => coefficiente,
+                    _ => return Err(errore("coefficiente invalido")),
+                };
+                testo.push_str(&format!(
+                    " {coefficiente} {}",
+                    colore(requisito.campo("colore"))?
+                ));
+            }
+            let utilizzo = match elemento.campo("utilizzo") {
+                None => 0,
+                Some(Valore::Numero(utilizzo)) => {
+                    utilizzo.parse().map_err(|_| errore("utilizzo invalido"))?
+                }
+                Some(..) => return Err(errore("utilizzo invalido")),
+            };
+
+            self.valida_regola(&testo).map_err(|e| errore(&e))?;
+            regole.push((testo, utilizzo));
+        }
+
+        // i colori sono aggiunti alla tavolozza solo se tutte le regole sono valide
+        Ok(regole
+            .into_iter()
+            .map(|(testo, utilizzo)| Regola {
+                utilizzo,
+                ..self._parsa_regola(&testo)
+            })
+            .collect())
+    }
+}
+
+/// Restituisce la coordinata a distanza `scostamento` da `origine`
+///
+/// # Returns
+/// * `Ok(i32)` - la coordinata
+/// * `Err(String)` - se la coordinata è fuori dal piano
+fn coordinata(origine: i32, scostamento: usize) -> Result<i32, String> {
+    i64::try_from(scostamento)
+        .ok()
+        .and_then(|scostamento| i32::try_from(origine as i64 + scostamento).ok())
+        .ok_or_else(|| String::from("piastrella fuori dal piano"))
+}
+
+/// Parsa una griglia di caratteri con legenda (vedi `Piano::importa_griglia`)
+fn parsa_griglia(griglia: &str, x: i32, y: i32) -> Result<Vec<Importata>, String> {
+    let mut righe = griglia.lines().map(|riga| riga.trim_end_matches('\r'));
+
+    let mut legenda: HashMap<char, (String, u32)> = HashMap::new();
+    for riga in righe.by_ref() {
+        if riga.trim().is_empty() {
+            break;
+        }
+
+        let parti: Vec<&str> = riga.split_whitespace().collect();
+        let carattere = match parti.as_slice() {
+            [carattere, ..] if carattere.chars().count() == 1 => carattere.chars().next().unwrap(),
+            _ => return Err(format!("legenda invalida ({riga})")),
+        };
+        let (colore, intensita) = match parti.as_slice() {
+            [_, colore, intensita] => match intensita.parse::<u32>() {
+                Ok(intensita) if intensita > 0 => (String::from(*colore), intensita),
+                _ => return Err(format!("legenda invalida (intensità {intensita})")),
+            },
+            _ => return Err(format!("legenda invalida ({riga})")),
+        };
+        if carattere == '.' || legenda.insert(carattere, (colore, intensita)).is_some() {
+            return Err(format!("legenda invalida (carattere {carattere})"));
+        }
+    }
+
+    let righe: Vec<&str> = righe.collect();
+    let mut piastrelle = Vec::new();
+    for (j, riga) in righe.iter().rev().enumerate() {
+        for (i, carattere) in riga.chars().enumerate() {
+            if carattere == '.' || carattere == ' ' {
+                continue;
+            }
+            let (colore, intensita) = legenda
+                .get(&carattere)
+                .ok_or_else(|| format!("carattere {carattere} non in legenda"))?;
+            piastrelle.push((
+                coordinata(x, i)?,
+                coordinata(y, j)?,
+                colore.clone(),
+                *intensita,
+            ));
+        }
+    }
+    Ok(piastrelle)
+}
+
+/// Intestazione del formato CSV delle piastrelle
+const INTESTAZIONE_CSV: &str = "x,y,colore,intensita";
+
+/// Parsa delle piastrelle in formato CSV (vedi `Piano::importa_csv`)
+fn parsa_csv(csv: &str) -> Result<Vec<Importata>, String> {
+    let mut piastrelle = Vec::new();
+    for (n, riga) in csv.lines().enumerate() {
+        let riga = riga.trim_end_matches('\r');
+        if riga.trim().is_empty() || (n == 0 && riga.trim() == INTESTAZIONE_CSV) {
+            continue;
+        }
+
+        let errore = || format!("csv invalido (riga {})", n + 1);
+        let campi = campi_csv(riga).ok_or_else(errore)?;
+        let [x, y, colore, intensita] = campi.as_slice() else {
+            return Err(errore());
+        };
+        let x: i32 = x.trim().parse().map_err(|_| errore())?;
+        let y: i32 = y.trim().parse().map_err(|_| errore())?;
+        if colore.is_empty() || colore.contains(char::is_whitespace) {
+            return Err(errore());
+        }
+        let intensita: u32 = match intensita.trim().parse() {
+            Ok(intensita) if intensita > 0 => intensita,
+            _ => return Err(errore()),
+        };
+        piastrelle.push((x, y, colore.clone(), intensita));
+    }
+    Ok(piastrelle)
+}
+
+/// Divide una riga CSV nei suoi campi, togliendo le virgolette dai campi racchiusi
+/// tra virgolette (dove `""` indica una virgoletta)
+///
+/// # Returns
+/// * `Some(Vec<String>)` - i campi della riga
+/// * `None` - se le virgolette non sono bilanciate
+fn campi_csv(riga: &str) -> Option<Vec<String>> {
+    let mut campi = Vec::new();
+    let mut caratteri = riga.chars().peekable();
+    loop {
+        let mut campo = String::new();
+        if caratteri.peek() == Some(&'"') {
+            caratteri.next();
+            loop {
+                match caratteri.next()? {
+                    '"' if caratteri.peek() == Some(&'"') => {
+                        caratteri.next();
+                        campo.push('"');
+                    }
+                    '"' => break,
+                    c => campo.push(c),
+                }
+            }
+            if caratteri.peek().is_some_and(|c| *c != ',') {
+                return None;
+            }
+        } else {
+            while let Some(c) = caratteri.next_if(|c| *c != ',') {
+                campo.push(c);
+            }
+        }
+        campi.push(campo);
+
+        if caratteri.next().is_none() {
+            return Some(campi);
+        }
+    }
+}
+
+/// Parsa un'immagine PPM (vedi `Piano::importa_ppm`)
+fn parsa_ppm(immagine: &[u8], x: i32, y: i32, intensita: u32) -> Result<Vec<Importata>, String> {
+    // intestazione: formato, larghezza, altezza, valore massimo, con commenti `#`
+    let mut intestazione: Vec<String> = Vec::new();
+    let mut i = 0;
+    while intestazione.len() < 4 {
+        match immagine.get(i) {
+            None => return Err(String::from("immagine invalida (intestazione incompleta)")),
+            Some(b'#') => {
+                while immagine.get(i).is_some_and(|c| *c != b'\n') {
+                    i += 1;
+                }
+            }
+            Some(c) if c.is_ascii_whitespace() => i += 1,
+            Some(..) => {
+                let inizio = i;
+                while immagine.get(i).is_some_and(|c| !c.is_ascii_whitespace()) {
+                    i += 1;
+                }
+                intestazione.push(String::from_utf8_lossy(&immagine[inizio..i]).into_owned());
+            }
+        }
+    }
+    // un solo spazio separa l'intestazione dai dati
+    i += 1;
+
+    let dimensione = |valore: &str| {
+        valore
+            .parse::<usize>()
+            .map_err(|_| format!("immagine invalida (dimensione {valore})"))
+    };
+    let larghezza = dimensione(&intestazione[1])?;
+    let altezza = dimensione(&intestazione[2])?;
+    if larghezza == 0 || altezza == 0 {
+        return Err(String::from("immagine invalida (dimensione 0)"));
+    }
+    // numero di campioni, 3 per pixel
+    let totale = larghezza
+        .checked_mul(altezza)
+        .and_then(|pixel| pixel.checked_mul(3))
+        .ok_or_else(|| String::from("immagine invalida (dimensione troppo grande)"))?;
+    let massimo: u32 = match intestazione[3].parse() {
+        Ok(massimo) if (1..=65535).contains(&massimo) => massimo,
+        _ => {
+            let massimo = &intestazione[3];
+            return Err(format!("immagine invalida (valore massimo {massimo})"));
+        }
+    };
+
+    let campioni: Vec<u32> = match intestazione[0].as_str() {
+        "P3" => immagine
+            .get(i..)
+            .map(String::from_utf8_lossy)
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|campione| {
+                campione
+                    .parse()
+                    .map_err(|_| format!("immagine invalida (campione {campione})"))
+            })
+            .collect::<Result<_, _>>()?,
+        "P6" if massimo < 256 => immagine
+            .get(i..)
+            .unwrap_or_default()
+            .iter()
+            .map(|c| *c as u32)
+            .collect(),
+        "P6" => immagine
+            .get(i..)
+            .unwrap_or_default()
+            .chunks_exact(2)
+            .map(|c| u32::from_be_bytes([0, 0, c[0], c[1]]))
+            .collect(),
+        formato => return Err(format!("immagine invalida (formato {formato})")),
+    };
+
+    if campioni.len() < totale {
+        return Err(String::from("immagine invalida (pixel mancanti)"));
+    }
+    if campioni.iter().any(|campione| *campione > massimo) {
+        return Err(String::from(
+            "immagine invalida (campione oltre il valore massimo)",
+        ));
+    }
+
+    let mut piastrelle = Vec::new();
+    for (j, riga) in campioni
+        .chunks_exact(larghezza * 3)
+        .take(altezza)
+        .enumerate()
+    {
+        for (i, pixel) in riga.chunks_exact(3).enumerate() {
+            let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|c| c * 255 / massimo);
+            if r == 0 && g == 0 && b == 0 {
+                continue;
+            }
+            let colore = format!("{r:02x}{g:02x}{b:02x}");
+            let ordinate = coordinata(y, altezza - 1 - j)?;
+            piastrelle.push((coordinata(x, i)?, ordinate, colore, intensita));
+        }
+    }
+    Ok(piastrelle)
+}
+
+/// Valore JSON, letto da `Valore::parsa`. I numeri sono mantenuti come testo,
+/// da convertire nel tipo atteso
+#[derive(PartialEq, Debug)]
+enum Valore {
+    Null,
+    Booleano(bool),
+    Numero(String),
+    Stringa(String),
+    Array(Vec<Valore>),
+    Oggetto(Vec<(String, Valore)>),
+}
+
+impl Valore {
+    /// Parsa un testo JSON, contenente un solo valore
+    ///
+    /// # Returns
+    /// * `Ok(Valore)` - il valore letto
+    /// * `Err(String)` - la descrizione dell'errore se il testo non è JSON valido
+    fn parsa(json: &str) -> Result<Valore, String> {
+        let mut lettore = LettoreJson {
+            caratteri: json.chars().collect(),
+            i: 0,
+        };
+        let valore = lettore.valore()?;
+        lettore.spazi();
+        match lettore.caratteri.get(lettore.i) {
+            None => Ok(valore),
+            Some(..) => Err(lettore.errore()),
+        }
+    }
+
+    /// Restituisce il campo `nome` di un oggetto
+    ///
+    /// # Returns
+    /// * `Some(&Valore)` - il valore del campo
+    /// * `None` - se il valore non è un oggetto o non ha il campo
+    fn campo(&self, nome: &str) -> Option<&Valore> {
+        match self {
+            Valore::Oggetto(campi) => campi
+                .iter()
+                .find(|(chiave, _)| chiave == nome)
+                .map(|(_, valore)| valore),
+            _ => None,
+        }
+    }
+}
+
+/// Parser a discesa ricorsiva di un testo JSON, posizionato al carattere `i`
+struct LettoreJson {
+    caratteri: Vec<char>,
+    i: usize,
+}
+
+impl LettoreJson {
+    fn errore(&self) -> String {
+        format!("json invalido (carattere {})", self.i)
+    }
+
+    fn spazi(&mut self) {
+        while self
+            .caratteri
+            .get(self.i)
+            .is_some_and(|c| c.is_whitespace())
+        {
+            self.i += 1;
+        }
+    }
+
+    /// Consuma il carattere `atteso`, dopo eventuali spazi
+    fn consuma(&mut self, atteso: char) -> Result<(), String> {
+        self.spazi();
+        if self.caratteri.get(self.i) != Some(&atteso) {
+            return Err(self.errore());
+        }
+        self.i += 1;
+        Ok(())
+    }
+
+    /// Consuma la parola `parola` se presente alla posizione corrente
+    fn parola(&mut self, parola: &str) -> bool {
+        let fine = self.i + parola.chars().count();
+        if self
+            .caratteri
+            .get(self.i..fine)
+            .is_some_and(|c| c.iter().copied().eq(parola.chars()))
+        {
+            self.i = fine;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn valore(&mut self) -> Result<Valore, String> {
+        self.spazi();
+        match self.caratteri.get(self.i).copied() {
+            Some('{') => self.oggetto(),
+            Some('[') => self.array(),
+            Some('"') => self.stringa().map(Valore::Stringa),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let inizio = self.i;
+                self.i += 1;
+                while self
+                    .caratteri
+                    .get(self.i)
+                    .is_some_and(|c| c.is_ascii_digit() || ".eE+-".contains(*c))
+                {
+                    self.i += 1;
+                }
+                Ok(Valore::Numero(
+                    self.caratteri[inizio..self.i].iter().collect(),
+                ))
+            }
+            _ if self.parola("null") => Ok(Valore::Null),
+            _ if self.parola("true") => Ok(Valore::Booleano(true)),
+            _ if self.parola("false") => Ok(Valore::Booleano(false)),
+            _ => Err(self.errore()),
+        }
+    }
+
+    fn oggetto(&mut self) -> Result<Valore, String> {
+        self.consuma('{')?;
+        let mut campi = Vec::new();
+        self.spazi();
+        if self.caratteri.get(self.i) == Some(&'}') {
+            self.i += 1;
+            return Ok(Valore::Oggetto(campi));
+        }
+        loop {
+            self.spazi();
+            let chiave = self.stringa()?;
+            self.consuma(':')?;
+            campi.push((chiave, self.valore()?));
+            self.spazi();
+            match self.caratteri.get(self.i) {
+                Some(',') => self.i += 1,
+                Some('}') => {
+                    self.i += 1;
+                    return Ok(Valore::Oggetto(campi));
+                }
+                _ => return Err(self.errore()),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Valore, String> {
+        self.consuma('[')?;
+        let mut elementi = Vec::new();
+        self.spazi();
+        if self.caratteri.get(self.i) == Some(&']') {
+            self.i += 1;
+            return Ok(Valore::Array(elementi));
+        }
+        loop {
+            elementi.push(self.valore()?);
+            self.spazi();
+            match self.caratteri.get(self.i) {
+                Some(',') => self.i += 1,
+                Some(']') => {
+                    self.i += 1;
+                    return Ok(Valore::Array(elementi));
+                }
+                _ => return Err(self.errore()),
+            }
+        }
+    }
+
+    fn stringa(&mut self) -> Result<String, String> {
+        self.consuma('"')?;
+        let mut stringa = String::new();
+        loop {
+            let carattere = *self.caratteri.get(self.i).ok_or_else(|| self.errore())?;
+            self.i += 1;
+            match carattere {
+                '"' => return Ok(stringa),
+                '\\' => {
+                    let escape = *self.caratteri.get(self.i).ok_or_else(|| self.errore())?;
+                    self.i += 1;
+                    match escape {
+                        '"' | '\\' | '/' => stringa.push(escape),
+                        'n' => stringa.push('\n'),
+                        'r' => stringa.push('\r'),
+                        't' => stringa.push('\t'),
+                        'b' => stringa.push('\u{8}'),
+                        'f' => stringa.push('\u{c}'),
+                        'u' => {
+                            let codice: String = self
+                                .caratteri
+                                .get(self.i..self.i + 4)
+                                .ok_or_else(|| self.errore())?
+                                .iter()
+                                .collect();
+                            self.i += 4;
+                            let carattere = u32::from_str_radix(&codice, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.errore())?;
+                            stringa.push(carattere);
+                        }
+                        _ => return Err(self.errore()),
+                    }
+                }
+                c => stringa.push(c),
+            }
+        }
+    }
+}
+
+/// Rappresentazione JSON di un valore
+pub trait Json {
+    fn json(&self) -> String;
+}
+
+impl Json for str {
+    fn json(&self) -> String {
+        let mut json = String::from("\"");
+        for carattere in self.chars() {
+            match carattere {
+                '"' => json.push_str("\\\""),
+                '\\' => json.push_str("\\\\"),
+                '\n' => json.push_str("\\n"),
+                '\r' => json.push_str("\\r"),
+                '\t' => json.push_str("\\t"),
+                c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+                c => json.push(c),
+            }
+        }
+        json.push('"');
+        json
+    }
+}
+
+impl Json for String {
+    fn json(&self) -> String {
+        self.as_str().json()
+    }
+}
+
+impl<T: Json + ?Sized> Json for &T {
+    fn json(&self) -> String {
+        (**self).json()
+    }
+}
+
+impl Json for u32 {
+    fn json(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Json for u64 {
+    fn json(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Json for usize {
+    fn json(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl<T: Json> Json for Option<T> {
+    fn json(&self) -> String {
+        match self {
+            Some(valore) => valore.json(),
+            None => String::from("null"),
+        }
+    }
+}
+
+impl<T: Json> Json for [T] {
+    fn json(&self) -> String {
+        let elementi: Vec<String> = self.iter().map(Json::json).collect();
+        format!("[{}]", elementi.join(","))
+    }
+}
+
+impl Json for Colorazione {
+    fn json(&self) -> String {
+        format!(
+            "{{\"colore\":{},\"intensita\":{}}}",
+            self.colore.json(),
+            self.intensita
+        )
+    }
+}
@@ -0,0 +1,329 @@
+use std::fmt::Write;
+
+/// Colori usati dalle piastrelle e dalle regole generate
+const COLORI: [&str; 7] = ["red", "yellow", "green", "blue", "black", "white", "purple"];
+
+/// Direzioni accettate da `pista`, con il relativo spostamento
+const DIREZIONI: [(&str, i64, i64); 8] = [
+    ("NN", 0, 1),
+    ("SS", 0, -1),
+    ("EE", 1, 0),
+    ("WW", -1, 0),
+    ("NE", 1, 1),
+    ("NW", -1, 1),
+    ("SE", 1, -1),
+    ("SW", -1, -1),
+];
+
+/// Generatore pseudocasuale (SplitMix64), deterministico a partire da un seme
+pub struct Casuale {
+    stato: u64,
+}
+
+impl Casuale {
+    /// Crea un generatore a partire da `seme`: generatori con lo stesso seme
+    /// producono la stessa sequenza
+    pub fn new(seme: u64) -> Self {
+        Self { stato: seme }
+    }
+
+    /// Restituisce il prossimo numero della sequenza
+    pub fn prossimo(&mut self) -> u64 {
+        self.stato = self.stato.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.stato;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Restituisce un numero tra `min` e `max`, estremi inclusi
+    ///
+    /// # Panics
+    /// * se `min` è maggiore di `max`
+    pub fn intervallo(&mut self, min: i64, max: i64) -> i64 {
+        assert!(min <= max, "intervallo vuoto");
+        let ampiezza = (max - min) as u64 + 1;
+        min + (self.prossimo() % ampiezza) as i64
+    }
+
+    /// Restituisce un elemento a caso di `elementi`
+    ///
+    /// # Panics
+    /// * se `elementi` è vuoto
+    pub fn scegli<'a, T>(&mut self, elementi: &'a [T]) -> &'a T {
+        &elementi[self.intervallo(0, elementi.len() as i64 - 1) as usize]
+    }
+}
+
+/// Scenario generato da `genera`
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Profilo {
+    /// piano piccolo quasi tutto acceso, interrogato per intero prima e dopo la propagazione
+    Denso,
+    /// poche piastrelle su un piano molto grande, con blocchi e spegnimenti
+    Sparso,
+    /// moltissime regole su un piano piccolo, con propagazioni e ordinamenti
+    Regole,
+    /// piste lunghe, che restano sul piano, su un piano quasi tutto acceso
+    Piste,
+    /// piste più brevi tra piastrelle vicine e lontane
+    Percorsi,
+}
+
+/// Nomi dei profili, nell'ordine in cui sono elencati nell'aiuto del generatore
+pub const PROFILI: [(&str, Profilo); 5] = [
+    ("denso", Profilo::Denso),
+    ("sparso", Profilo::Sparso),
+    ("regole", Profilo::Regole),
+    ("piste", Profilo::Piste),
+    ("percorsi", Profilo::Percorsi),
+];
+
+impl Profilo {
+    /// Cerca il profilo con nome `nome` (vedi `PROFILI`)
+    ///
+    /// # Returns
+    /// * `Some(Profilo)` - il profilo corrispondente
+    /// * `None` - se il profilo non esiste
+    pub fn cerca(nome: &str) -> Option<Profilo> {
+        PROFILI
+            .iter()
+            .find(|(profilo, _)| *profilo == nome)
+            .map(|(_, profilo)| *profilo)
+    }
+
+    /// Restituisce la scala massima accettata da `genera` per il profilo. I comandi
+    /// crescono con la scala in `sparso` e con il suo quadrato negli altri profili, che
+    /// coprono tutto il piano: ogni massimo limita l'output a circa 100 MB
+    pub fn scala_massima(&self) -> u32 {
+        match self {
+            Profilo::Denso => 50,
+            Profilo::Sparso => 1000,
+            Profilo::Regole => 100,
+            Profilo::Piste => 30,
+            Profilo::Percorsi => 25,
+        }
+    }
+}
+
+/// Genera una sequenza di comandi, una riga per comando e terminata da `q`, secondo
+/// lo scenario `profilo`. La sequenza dipende solo dai parametri
+///
+/// # Arguments
+/// * `profilo` - scenario da generare
+/// * `seme` - seme del generatore pseudocasuale
+/// * `scala` - fattore di scala del piano e del numero di comandi, da 1 alla scala
+///   massima del profilo (vedi `Profilo::scala_massima`)
+///
+/// # Returns
+/// * `String` che rappresenta i comandi generati
+///
+/// # Panics
+/// * se `scala` è 0 o maggiore della scala massima del profilo
+pub fn genera(profilo: Profilo, seme: u64, scala: u32) -> String {
+    assert!(
+        (1..=profilo.scala_massima()).contains(&scala),
+        "scala non valida"
+    );
+    let s = scala as usize;
+
+    let limite = match profilo {
+        Profilo::Denso => 20,
+        Profilo::Sparso => 1000,
+        Profilo::Regole => 10,
+        Profilo::Piste => 40,
+        Profilo::Percorsi => 50,
+    } * scala as i32;
+    let mut generatore = Generatore {
+        casuale: Casuale::new(seme),
+        limite,
+        comandi: String::new(),
+    };
+    let area = (limite as usize + 1).pow(2);
+
+    match profilo {
+        Profilo::Denso => {
+            generatore.piastrelle(area * 2);
+            generatore.stampa_tutti();
+            generatore.regole(10);
+            generatore.blocchi(20 * s);
+            generatore.propaga(50 * s, true);
+            generatore.stampa_tutti();
+        }
+        Profilo::Sparso => {
+            generatore.piastrelle(500 * s);
+            generatore.blocchi(200 * s);
+            generatore.spegni(100 * s);
+            generatore.stati(200 * s);
+            generatore.regole(10);
+            generatore.propaga(100 * s, false);
+            generatore.blocchi(200 * s);
+        }
+        Profilo::Regole => {
+            generatore.piastrelle(area);
+            generatore.regole(300 * s);
+            generatore.propaga(300 * s, true);
+            generatore.stampa_tutti();
+        }
+        Profilo::Piste => {
+            generatore.riempi();
+            generatore.piastrelle(area / 2);
+            generatore.spegni(area / 200);
+            generatore.piste(200 * s, 4 * limite as usize);
+        }
+        Profilo::Percorsi => {
+            generatore.piastrelle(area * 2);
+            generatore.lung(200 * s);
+            generatore.spegni(area / 10);
+            generatore.lung(200 * s);
+        }
+    }
+
+    generatore.comandi.push_str("q\n");
+    generatore.comandi
+}
+
+/// Stato della generazione: piastrelle generate nel quadrato da (0, 0) a (`limite`, `limite`)
+struct Generatore {
+    casuale: Casuale,
+    limite: i32,
+    comandi: String,
+}
+
+impl Generatore {
+    /// Restituisce una piastrella a caso nel quadrato
+    fn piastrella(&mut self) -> (i64, i64) {
+        let limite = self.limite as i64;
+        (
+            self.casuale.intervallo(0, limite),
+            self.casuale.intervallo(0, limite),
+        )
+    }
+
+    /// Genera `numero` colorazioni di piastrelle a caso, con colore e intensità a caso
+    fn piastrelle(&mut self, numero: usize) {
+        for _ in 0..numero {
+            let (x, y) = self.piastrella();
+            let colore = self.casuale.scegli(&COLORI);
+            let intensita = self.casuale.intervallo(1, 1000);
+            writeln!(self.comandi, "C {x} {y} {colore} {intensita}").unwrap();
+        }
+    }
+
+    /// Colora tutto il quadrato con un solo rettangolo
+    fn riempi(&mut self) {
+        let limite = self.limite;
+        let colore = self.casuale.scegli(&COLORI);
+        let intensita = self.casuale.intervallo(1, 1000);
+        writeln!(self.comandi, "F 0 0 {limite} {limite} {colore} {intensita}").unwrap();
+    }
+
+    /// Genera `numero` spegnimenti di piastrelle a caso
+    fn spegni(&mut self, numero: usize) {
+        for _ in 0..numero {
+            let (x, y) = self.piastrella();
+            writeln!(self.comandi, "S {x} {y}").unwrap();
+        }
+    }
+
+    /// Genera `numero` richieste dello stato di piastrelle a caso
+    fn stati(&mut self, numero: usize) {
+        for _ in 0..numero {
+            let (x, y) = self.piastrella();
+            writeln!(self.comandi, "? {x} {y}").unwrap();
+        }
+    }
+
+    /// Genera una richiesta di stato per ogni piastrella del quadrato, riga per riga
+    fn stampa_tutti(&mut self) {
+        for y in 0..=self.limite {
+            for x in 0..=self.limite {
+                writeln!(self.comandi, "? {x} {y}").unwrap();
+            }
+        }
+    }
+
+    /// Genera `numero` regole con colori dei requisiti distinti e somma dei
+    /// coefficienti al più 8, seguite da `s`
+    fn regole(&mut self, numero: usize) {
+        for _ in 0..numero {
+            let massimo = self.casuale.intervallo(1, 8);
+            let colore = self.casuale.scegli(&COLORI);
+            write!(self.comandi, "r {colore}").unwrap();
+
+            let mut disponibili = COLORI.to_vec();
+            let mut somma = 0;
+            while somma < massimo && !disponibili.is_empty() {
+                let coefficiente = self.casuale.intervallo(1, massimo - somma);
+                let indice = self.casuale.intervallo(0, disponibili.len() as i64 - 1);
+                let colore = disponibili.remove(indice as usize);
+                write!(self.comandi, " {coefficiente} {colore}").unwrap();
+                somma += coefficiente;
+            }
+            self.comandi.push('\n');
+        }
+        self.comandi.push_str("s\n");
+    }
+
+    /// Genera `numero` richieste di blocco e di blocco omogeneo di piastrelle a caso
+    fn blocchi(&mut self, numero: usize) {
+        for _ in 0..numero {
+            let (x, y) = self.piastrella();
+            writeln!(self.comandi, "b {x} {y}\nB {x} {y}").unwrap();
+        }
+    }
+
+    /// Genera `numero` propagazioni, alternando `p` e `P`, con un ordinamento delle
+    /// regole ogni 10 propagazioni circa se `ordina`
+    fn propaga(&mut self, numero: usize, ordina: bool) {
+        for i in 0..numero {
+            let (x, y) = self.piastrella();
+            let comando = if i % 2 == 0 { "p" } else { "P" };
+            writeln!(self.comandi, "{comando} {x} {y}").unwrap();
+            if ordina && self.casuale.intervallo(1, 10) == 1 {
+                self.comandi.push_str("o\ns\n");
+            }
+        }
+    }
+
+    /// Genera `numero` piste, ognuna lunga da 1 a `lunghezza` direzioni, che non escono
+    /// dal quadrato
+    fn piste(&mut self, numero: usize, lunghezza: usize) {
+        let limite = self.limite as i64;
+        for _ in 0..numero {
+            let (mut x, mut y) = self.piastrella();
+            write!(self.comandi, "t {x} {y}").unwrap();
+            for _ in 0..self.casuale.intervallo(1, lunghezza as i64) {
+                let (direzione, dx, dy) = loop {
+                    let (direzione, dx, dy) = *self.casuale.scegli(&DIREZIONI);
+                    if (0..=limite).contains(&(x + dx)) && (0..=limite).contains(&(y + dy)) {
+                        break (direzione, dx, dy);
+                    }
+                };
+                (x, y) = (x + dx, y + dy);
+                write!(self.comandi, " {direzione}").unwrap();
+            }
+            self.comandi.push('\n');
+        }
+    }
+
+    /// Genera `numero` richieste di pista più breve, alternando piastrelle vicine
+    /// (distanti al più 10) e piastrelle qualsiasi del quadrato
+    fn lung(&mut self, numero: usize) {
+        let limite = self.limite as i64;
+        for i in 0..numero {
+            let (x1, y1) = self.piastrella();
+            let (x2, y2) = if i % 2 == 0 {
+                (
+                    self.casuale
+                        .intervallo((x1 - 10).max(0), (x1 + 10).min(limite)),
+                    self.casuale
+                        .intervallo((y1 - 10).max(0), (y1 + 10).min(limite)),
+                )
+            } else {
+                self.piastrella()
+            };
+            writeln!(self.comandi, "L {x1} {y1} {x2} {y2}").unwrap();
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::tavolozza::Colore;
+use crate::{Regola, Requisito, ADIACENTI};
+
+/// Istogramma dei colori delle piastrelle adiacenti ad una piastrella: al più
+/// `ADIACENTI.len()` colori distinti, con il numero di piastrelle di ciascuno.
+/// Sta tutto sullo stack, calcolarlo non alloca memoria
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Intorno {
+    colori: [(Colore, u8); ADIACENTI.len()],
+    distinti: usize,
+}
+
+impl Intorno {
+    /// Conta una piastrella adiacente di colore `colore`
+    pub fn aggiungi(&mut self, colore: Colore) {
+        let distinti = &mut self.colori[..self.distinti];
+        match distinti.iter_mut().find(|(c, _)| *c == colore) {
+            Some((_, n)) => *n += 1,
+            None => {
+                self.colori[self.distinti] = (colore, 1);
+                self.distinti += 1;
+            }
+        }
+    }
+
+    /// Restituisce il numero di piastrelle adiacenti di colore `colore`
+    pub fn conta(&self, colore: Colore) -> u8 {
+        self.iter()
+            .find(|(c, _)| *c == colore)
+            .map_or(0, |(_, n)| *n)
+    }
+
+    /// Restituisce i colori presenti, con il numero di piastrelle di ciascuno
+    pub fn iter(&self) -> impl Iterator<Item = &(Colore, u8)> {
+        self.colori[..self.distinti].iter()
+    }
+
+    /// Restituisce il primo requisito di `regola` **non** soddisfatto dall'intorno
+    ///
+    /// # Returns
+    /// * `Some(Requisito)` - il primo requisito non soddisfatto
+    /// * `None` - se la regola è applicabile
+    pub fn requisito_mancante<'a>(&self, regola: &'a Regola) -> Option<&'a Requisito> {
+        regola.requisiti.iter().find(
+            |Requisito {
+                 coefficiente,
+                 colore,
+             }| self.conta(*colore) < *coefficiente,
+        )
+    }
+}
+
+/// Indice delle regole per colore: ogni regola è elencata, in ordine di posizione,
+/// sotto il colore del suo primo requisito. Una regola può essere applicabile solo se
+/// quel colore è nell'intorno, quindi basta controllare le regole elencate sotto i
+/// (al più 8) colori dell'intorno, invece di tutte
+#[derive(Debug)]
+pub struct IndiceRegole {
+    /// posizioni delle regole, indicizzate dal colore del primo requisito
+    per_colore: Vec<Vec<usize>>,
+}
+
+impl IndiceRegole {
+    /// Costruisce l'indice di `regole`
+    pub fn new(regole: &[Regola]) -> Self {
+        let mut per_colore: Vec<Vec<usize>> = Vec::new();
+        for (i, regola) in regole.iter().enumerate() {
+            // le regole hanno sempre almeno un requisito
+            let colore = regola.requisiti[0].colore.indice();
+            if colore >= per_colore.len() {
+                per_colore.resize_with(colore + 1, Vec::new);
+            }
+            per_colore[colore].push(i);
+        }
+        Self { per_colore }
+    }
+
+    /// Restituisce la posizione della *prima* regola di `regole` applicabile con
+    /// l'intorno `intorno`, come se le regole fossero controllate tutte in ordine
+    ///
+    /// # Arguments
+    /// * `regole` - le regole da cui è stato costruito l'indice
+    /// * `intorno` - colori dell'intorno della piastrella
+    ///
+    /// # Returns
+    /// * `Some(usize)` - la posizione della prima regola applicabile
+    /// * `None` - se nessuna regola è applicabile
+    pub fn prima_applicabile(&self, regole: &[Regola], intorno: &Intorno) -> Option<usize> {
+        let mut prima: Option<usize> = None;
+        for (colore, _) in intorno.iter() {
+            let Some(candidate) = self.per_colore.get(colore.indice()) else {
+                continue;
+            };
+            for &i in candidate {
+                // le regole successive a quella già trovata non interessano
+                if prima.is_some_and(|prima| i >= prima) {
+                    break;
+                }
+                if intorno.requisito_mancante(&regole[i]).is_none() {
+                    prima = Some(i);
+                    break;
+                }
+            }
+        }
+        prima
+    }
+}
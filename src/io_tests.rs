@@ -1,74 +1,145 @@
-use std::io::Read;
+use std::fs;
+use std::panic;
+use std::path::PathBuf;
 
 #[cfg(test)]
 use crate::*;
 
+/// Esegue ogni coppia `nome.in`/`nome.out` in `inputs/`
 #[test]
-fn test_small() {
-    run_test(String::from("small"));
-    run_test(String::from("small2"));
-    run_test(String::from("small3"));
+fn test_inputs() {
+    run_cartella("inputs", Traccia::Nessuna);
 }
 
+/// Esegue ogni coppia `nome.in`/`nome.out` in `inputs/traccia/`, con la traccia delle mutazioni
 #[test]
-fn test_spegni() {
-    run_test(String::from("spegni"));
+fn test_traccia() {
+    run_cartella("inputs/traccia", Traccia::Mutazioni);
 }
 
-#[test]
-fn test_blocco() {
-    run_test(String::from("blocco"));
-}
-
-#[test]
-fn test_propaga() {
-    run_test(String::from("propaga"));
-}
+// --- utils functions ---
 
-#[test]
-fn test_tutto() {
-    run_test(String::from("tutto"));
-}
+/// Esegue tutte le coppie di input e output attesi della cartella, fallendo con la
+/// differenza di ogni coppia che non corrisponde. L'output di ogni input è scritto in
+/// `test_nome.out`, rimosso se corrisponde a quello atteso
+fn run_cartella(cartella: &str, traccia: Traccia) {
+    let cartella = Path::new(cartella);
+    let (coppie, orfani) = coppie(cartella);
+    assert!(!coppie.is_empty(), "nessun input in {}", cartella.display());
+    for orfano in orfani {
+        eprintln!("{}: output atteso senza input", orfano.display());
+    }
 
-// --- utils functions ---
+    let mut errori = Vec::new();
+    for nome in coppie {
+        let input = cartella.join(format!("{nome}.in"));
+        let output = cartella.join(format!("{nome}.out"));
+        let test = cartella.join(format!("test_{nome}.out"));
 
-fn run_test(name: String) {
-    run(Some(input(&name)), Some(test(&name)));
-    assert!(is_same_file(output(&name), test(&name)).expect(""));
-}
+        if let Err(errore) = run_test(&input, &test, traccia) {
+            errori.push(format!("{}: {errore}", input.display()));
+            continue;
+        }
+        match differenza(&input, &output, &test, traccia) {
+            Some(differenza) => errori.push(format!("{}: {differenza}", input.display())),
+            None => fs::remove_file(&test).unwrap(),
+        }
+    }
 
-fn input(s: &str) -> String {
-    format!("inputs/{}.in", s)
+    assert!(errori.is_empty(), "\n{}\n", errori.join("\n\n"));
 }
 
-fn output(s: &str) -> String {
-    format!("inputs/{}.out", s)
+/// Cerca nella cartella i file `nome.in` con il relativo `nome.out`
+///
+/// # Returns
+/// * i nomi delle coppie trovate, ordinati, e i file `.out` senza input
+///   (esclusi gli output dei test `test_*.out`)
+fn coppie(cartella: &Path) -> (Vec<String>, Vec<PathBuf>) {
+    let mut coppie = Vec::new();
+    let mut orfani = Vec::new();
+    for file in fs::read_dir(cartella).unwrap() {
+        let file = file.unwrap().path();
+        let nome = file.file_stem().unwrap().to_string_lossy().into_owned();
+        match file.extension().and_then(|estensione| estensione.to_str()) {
+            Some("in") if file.with_extension("out").exists() => coppie.push(nome),
+            Some("out") if !nome.starts_with("test_") && !file.with_extension("in").exists() => {
+                orfani.push(file)
+            }
+            _ => (),
+        }
+    }
+    coppie.sort();
+    orfani.sort();
+    (coppie, orfani)
 }
 
-fn test(s: &str) -> String {
-    format!("inputs/test_{}.out", s)
+/// Esegue `run` sull'input, restituendo il messaggio di errore se `run` va in panic
+fn run_test(input: &Path, test: &Path, traccia: Traccia) -> Result<(), String> {
+    let (input, test) = (input.display().to_string(), test.display().to_string());
+    panic::catch_unwind(|| run(Some(input), Some(test), traccia)).map_err(|errore| {
+        let messaggio = errore
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| errore.downcast_ref::<&str>().map(|s| s.to_string()));
+        format!("errore ({})", messaggio.unwrap_or_default())
+    })
 }
 
-// https://users.rust-lang.org/t/efficient-way-of-checking-if-two-files-have-the-same-content/74735
-fn is_same_file(file1: String, file2: String) -> Result<bool, std::io::Error> {
-    let f1 = File::open(file1)?;
-    let f2 = File::open(file2)?;
-
-    // Check if file sizes are different
-    if f1.metadata().unwrap().len() != f2.metadata().unwrap().len() {
-        return Ok(false);
+/// Confronta l'output `test` con l'output atteso `output`
+///
+/// # Returns
+/// * `None` - se i due output sono uguali
+/// * `Some(String)` - la prima riga diversa, con il contenuto atteso e ottenuto
+///   e il comando di input che l'ha prodotta
+fn differenza(input: &Path, output: &Path, test: &Path, traccia: Traccia) -> Option<String> {
+    let atteso = fs::read_to_string(output).unwrap();
+    let ottenuto = fs::read_to_string(test).unwrap();
+    if atteso == ottenuto {
+        return None;
     }
 
-    // Use buf readers since they are much faster
-    let f1 = BufReader::new(f1);
-    let f2 = BufReader::new(f2);
+    let atteso: Vec<&str> = atteso.lines().collect();
+    let ottenuto: Vec<&str> = ottenuto.lines().collect();
+    let riga = (0..)
+        .find(|&i| atteso.get(i) != ottenuto.get(i) || i >= atteso.len())
+        .unwrap();
+    let mostra =
+        |riga: Option<&&str>| riga.map_or(String::from("<fine output>"), |r| format!("{r:?}"));
+
+    Some(format!(
+        "riga {} diversa\n  atteso:   {}\n  ottenuto: {}\n  comando:  {}",
+        riga + 1,
+        mostra(atteso.get(riga)),
+        mostra(ottenuto.get(riga)),
+        comando(input, test, riga, traccia).unwrap_or(String::from("<nessuno>"))
+    ))
+}
 
-    // Do a byte to byte comparison of the two files
-    for (b1, b2) in f1.bytes().zip(f2.bytes()) {
-        if b1.unwrap() != b2.unwrap() {
-            return Ok(false);
-        }
+/// Trova il comando che ha prodotto la riga `riga` dell'output. Senza traccia esegue di
+/// nuovo l'input con la traccia delle righe, altrimenti il comando è già nell'output
+///
+/// # Returns
+/// * `Some(String)` - il comando nel formato `numero comando` (vedi `Traccia::Righe`)
+/// * `None` - se l'output ha meno righe
+fn comando(input: &Path, test: &Path, riga: usize, traccia: Traccia) -> Option<String> {
+    let ottenuto = fs::read_to_string(test).unwrap();
+    let ottenuta = ottenuto.lines().nth(riga)?;
+    if traccia != Traccia::Nessuna {
+        return ottenuta
+            .split_once(": ")
+            .map(|(comando, _)| String::from(comando));
     }
 
-    Ok(true)
+    let tracciato = test.with_extension("traccia");
+    run_test(input, &tracciato, Traccia::Righe).ok()?;
+    let contenuto = fs::read_to_string(&tracciato).unwrap();
+    fs::remove_file(&tracciato).unwrap();
+
+    // le righe di output senza traccia sono le righe tracciate senza prefisso
+    contenuto
+        .lines()
+        .nth(riga)?
+        .strip_suffix(ottenuta)
+        .and_then(|prefisso| prefisso.strip_suffix(": "))
+        .map(String::from)
 }
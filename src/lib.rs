@@ -0,0 +1,2269 @@
+use std::any::Any;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use archivio::{Archivio, Blocchi};
+use formati::Json;
+use indice::{IndiceRegole, Intorno};
+use script::Interprete;
+use spaziale::{IndiceSpaziale, Riepilogo};
+use tavolozza::{Colore, Tavolozza};
+
+/// Piastrella rappresentata da x e y
+#[derive(Eq, Hash, PartialEq, Clone, Debug)]
+struct Piastrella {
+    x: i32,
+    y: i32,
+}
+
+/// Colorazione di una piastrella: colore e intensità
+#[derive(PartialEq, Clone, Debug)]
+struct Colorazione {
+    colore: String,
+    intensita: u32,
+}
+
+/// Modifica di una piastrella: colorazione prima e dopo, `None` se spenta
+#[derive(PartialEq, Debug)]
+struct Modifica {
+    piastrella: Piastrella,
+    prima: Option<Colorazione>,
+    dopo: Option<Colorazione>,
+}
+
+/// Colorazione di una piastrella come memorizzata nel piano, con il colore
+/// della tavolozza del piano al posto del nome
+#[derive(PartialEq, Clone, Copy, Debug)]
+struct Tinta {
+    colore: Colore,
+    intensita: u32,
+}
+
+/// Requisito di una regola: un coefficiente da 1 a 8 e un colore
+#[derive(PartialEq, Debug)]
+struct Requisito {
+    coefficiente: u8,
+    colore: Colore,
+}
+
+/// Regola: dei requisiti, un colore "finale" e il suo utilizzo
+#[derive(PartialEq, Debug)]
+struct Regola {
+    requisiti: Vec<Requisito>,
+    colore: Colore,
+    utilizzo: u32,
+}
+
+/// Regola letta da un testo, con i nomi dei colori: colore e requisiti (coefficiente e colore)
+type TestoRegola<'a> = (&'a str, Vec<(u8, &'a str)>);
+
+/// Anomalia di una regola, individuata dall'analisi statica delle regole
+#[derive(PartialEq, Debug)]
+enum Anomalia {
+    /// regola uguale ad una regola precedente (`originale`)
+    Duplicata { indice: usize, originale: usize },
+    /// regola mai raggiunta perché una regola precedente (`da`) è sempre applicabile prima
+    Oscurata { indice: usize, da: usize },
+}
+
+/// Formatta un'anomalia come `indice descrizione`
+impl fmt::Display for Anomalia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Anomalia::Duplicata { indice, originale } => {
+                write!(f, "{indice} duplicata di {originale}")
+            }
+            Anomalia::Oscurata { indice, da } => write!(f, "{indice} oscurata da {da}"),
+        }
+    }
+}
+
+/// Applicazione di una regola ad una piastrella durante la propagazione di un blocco
+#[derive(PartialEq, Debug)]
+struct Applicazione {
+    piastrella: Piastrella,
+    colore_precedente: Colore,
+    colore: Colore,
+    regola: usize,
+}
+
+/// Ordine di visita delle piastrelle di un blocco nella propagazione sequenziale
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Ordine {
+    /// visita in ampiezza a partire dalla piastrella indicata
+    Bfs,
+    /// per ordinate crescenti, a parità di ordinate per ascisse crescenti
+    Righe,
+    /// per ascisse crescenti, a parità di ascisse per ordinate crescenti
+    Coordinate,
+}
+
+/// Piano, l'intero sistema:
+/// - delle piastrelle con relativo colore
+/// - delle regole con relativo utilizzo
+/// - della tavolozza dei colori usati da piastrelle e regole
+/// - dell'indice delle regole, ricostruito alla prima propagazione dopo ogni
+///   modifica delle regole
+/// - dell'indice spaziale delle piastrelle, aggiornato ad ogni modifica delle piastrelle
+/// - delle piastrelle modificate, con la tinta precedente, se la registrazione è attiva
+/// - del numero di thread usati dalle operazioni in sola lettura su molte piastrelle
+///
+/// Le piastrelle sono memorizzate nell'archivio `A`: `Blocchi` di default,
+/// `HashMap<Piastrella, Tinta>` come alternativa
+struct Piano<A: Archivio = Blocchi> {
+    piastrelle: A,
+    regole: Vec<Regola>,
+    tavolozza: Tavolozza,
+    indice: OnceLock<IndiceRegole>,
+    spaziale: IndiceSpaziale,
+    modificate: Option<HashMap<Piastrella, Option<Tinta>>>,
+    thread: usize,
+}
+
+const ADIACENTI: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+impl Piano {
+    /// Crea un nuovo piano vuoto, senza piastrelle e senza regole, con l'archivio
+    /// di default
+    ///
+    /// # Returns
+    /// * un `Piano` vuoto
+    fn new() -> Self {
+        Self::con_archivio()
+    }
+}
+
+/// Implementazione metodi per Piano
+impl<A: Archivio> Piano<A> {
+    /// Crea un nuovo piano vuoto, senza piastrelle e senza regole, con l'archivio `A`
+    ///
+    /// # Returns
+    /// * un `Piano` vuoto
+    fn con_archivio() -> Self {
+        Self {
+            piastrelle: A::default(),
+            regole: Vec::new(),
+            tavolozza: Tavolozza::default(),
+            indice: OnceLock::new(),
+            spaziale: IndiceSpaziale::default(),
+            modificate: None,
+            thread: parallelo::disponibili(),
+        }
+    }
+
+    /// Imposta il numero di thread usati per simulare la propagazione di un blocco
+    /// e per etichettare i blocchi del piano. Il risultato **non** dipende dal numero
+    /// di thread
+    ///
+    /// # Arguments
+    /// * `thread` - numero di thread
+    ///
+    /// # Panics
+    /// * se `thread` è 0
+    fn imposta_thread(&mut self, thread: usize) {
+        if thread == 0 {
+            panic!("il numero di thread deve essere maggiore di 0");
+        }
+        self.thread = thread;
+    }
+
+    /// Colora una piastrella indicata da `x` e `y`, impostando il suo `colore`
+    /// e la sua `intensita` a 1, modificando il Piano
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella da colorare
+    /// * `y` - ordinate della piastrella da colorare
+    /// * `colore` - colore della piastrella
+    /// * `intensita` - intensità della piastrella
+    ///
+    /// # Panics
+    /// * se `colore` è una stringa vuota
+    /// * se `intensita` è minore o uguale a 0
+    fn colora(&mut self, x: i32, y: i32, colore: String, intensita: u32) {
+        assert!(!colore.is_empty(), "colore non valido");
+        assert!(intensita > 0, "intensità non valida");
+
+        let colore = self.tavolozza.interna(&colore);
+        self._accendi(Piastrella { x, y }, Tinta { intensita, colore });
+    }
+
+    /// Spegne una piastrella indicata da `x` e `y`, modificando il Piano
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella da spegnere
+    /// * `y` - ordinate della piastrella da spegnere
+    fn spegni(&mut self, x: i32, y: i32) {
+        self._spegni(&Piastrella { x, y });
+    }
+
+    /// Accende la piastrella con la tinta `tinta`, aggiornando l'indice spaziale.
+    /// Ogni modifica delle piastrelle passa da qui o da `_spegni`
+    fn _accendi(&mut self, piastrella: Piastrella, tinta: Tinta) {
+        self.spaziale.aggiungi(&piastrella, &tinta);
+        let precedente = self.piastrelle.insert(piastrella.clone(), tinta);
+        if let Some(precedente) = precedente {
+            self.spaziale.togli(&piastrella, &precedente);
+        }
+        Self::_registra(&mut self.modificate, &piastrella, precedente);
+    }
+
+    /// Spegne la piastrella, se accesa, aggiornando l'indice spaziale
+    fn _spegni(&mut self, piastrella: &Piastrella) {
+        if let Some(tinta) = self.piastrelle.remove(piastrella) {
+            self.spaziale.togli(piastrella, &tinta);
+            Self::_registra(&mut self.modificate, piastrella, Some(tinta));
+        }
+    }
+
+    /// Registra la scrittura di una piastrella, se la registrazione è attiva: conta
+    /// solo la tinta precedente alla *prima* scrittura (vedi `modifiche`)
+    fn _registra(
+        modificate: &mut Option<HashMap<Piastrella, Option<Tinta>>>,
+        piastrella: &Piastrella,
+        precedente: Option<Tinta>,
+    ) {
+        if let Some(modificate) = modificate {
+            modificate.entry(piastrella.clone()).or_insert(precedente);
+        }
+    }
+
+    /// Attiva la registrazione delle piastrelle modificate, scartando quelle già registrate
+    fn registra_modifiche(&mut self) {
+        self.modificate = Some(HashMap::new());
+    }
+
+    /// Restituisce le piastrelle modificate dall'attivazione della registrazione (vedi
+    /// `registra_modifiche`) o dalla chiamata precedente, e ricomincia a registrare.
+    /// Il costo dipende solo dalle piastrelle scritte, non da quelle del piano
+    ///
+    /// # Returns
+    /// * le piastrelle con colorazione diversa da quella precedente, con la colorazione
+    ///   prima e dopo (`None` se spenta), ordinate per ascisse e poi per ordinate
+    fn modifiche(&mut self) -> Vec<Modifica> {
+        let Some(modificate) = self.modificate.replace(HashMap::new()) else {
+            return Vec::new();
+        };
+        let mut modifiche: Vec<Modifica> = modificate
+            .into_iter()
+            .filter(|(piastrella, precedente)| {
+                self.piastrelle.get(piastrella) != precedente.as_ref()
+            })
+            .map(|(piastrella, precedente)| Modifica {
+                prima: precedente.map(|tinta| self._colorazione(&tinta)),
+                dopo: self.stato(piastrella.x, piastrella.y),
+                piastrella,
+            })
+            .collect();
+        modifiche.sort_by_key(|Modifica { piastrella, .. }| (piastrella.x, piastrella.y));
+        modifiche
+    }
+
+    /// Colora tutte le piastrelle del rettangolo con vertici opposti (`x1`, `y1`) e
+    /// (`x2`, `y2`), estremi inclusi, come `colora`, modificando il Piano
+    ///
+    /// # Arguments
+    /// * `x1`, `y1` - vertice del rettangolo
+    /// * `x2`, `y2` - vertice opposto del rettangolo
+    /// * `colore` - colore delle piastrelle
+    /// * `intensita` - intensità delle piastrelle
+    ///
+    /// # Panics
+    /// * se `colore` o `intensita` non sono validi (vedi `colora`)
+    fn colora_rettangolo(
+        &mut self,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+        colore: String,
+        intensita: u32,
+    ) {
+        for x in x1.min(x2)..=x1.max(x2) {
+            for y in y1.min(y2)..=y1.max(y2) {
+                self.colora(x, y, colore.clone(), intensita);
+            }
+        }
+    }
+
+    /// Spegne tutte le piastrelle del rettangolo con vertici opposti (`x1`, `y1`) e
+    /// (`x2`, `y2`), estremi inclusi, modificando il Piano
+    ///
+    /// # Arguments
+    /// * `x1`, `y1` - vertice del rettangolo
+    /// * `x2`, `y2` - vertice opposto del rettangolo
+    fn spegni_rettangolo(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) {
+        let (xmin, xmax) = (x1.min(x2), x1.max(x2));
+        let (ymin, ymax) = (y1.min(y2), y1.max(y2));
+        // fino a 2^64 piastrelle, non sta in un i64
+        let area = (xmax as i128 - xmin as i128 + 1) * (ymax as i128 - ymin as i128 + 1);
+
+        // se il rettangolo è più grande del piano conviene scorrere le piastrelle accese
+        if area > self.piastrelle.len() as i128 {
+            let (spaziale, modificate) = (&mut self.spaziale, &mut self.modificate);
+            self.piastrelle.retain(|piastrella, tinta| {
+                let dentro =
+                    (xmin..=xmax).contains(&piastrella.x) && (ymin..=ymax).contains(&piastrella.y);
+                if dentro {
+                    spaziale.togli(piastrella, tinta);
+                    Self::_registra(modificate, piastrella, Some(*tinta));
+                }
+                !dentro
+            });
+        } else {
+            for x in xmin..=xmax {
+                for y in ymin..=ymax {
+                    self.spegni(x, y);
+                }
+            }
+        }
+    }
+
+    /// Colora le piastrelle del segmento da (`x1`, `y1`) a (`x2`, `y2`), estremi inclusi,
+    /// approssimato con l'algoritmo di Bresenham, come `colora`, modificando il Piano
+    ///
+    /// # Arguments
+    /// * `x1`, `y1` - inizio del segmento
+    /// * `x2`, `y2` - fine del segmento
+    /// * `colore` - colore delle piastrelle
+    /// * `intensita` - intensità delle piastrelle
+    ///
+    /// # Panics
+    /// * se `colore` o `intensita` non sono validi (vedi `colora`)
+    fn colora_linea(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, colore: String, intensita: u32) {
+        // in i64 le differenze tra coordinate (e il loro doppio) non traboccano
+        let (x1, y1, x2, y2) = (x1 as i64, y1 as i64, x2 as i64, y2 as i64);
+        let dx = (x2 - x1).abs();
+        let dy = -(y2 - y1).abs();
+        let (sx, sy) = ((x2 - x1).signum(), (y2 - y1).signum());
+        let mut errore = dx + dy;
+        let (mut x, mut y) = (x1, y1);
+
+        loop {
+            // i punti del segmento restano tra gli estremi, quindi sono degli i32
+            self.colora(x as i32, y as i32, colore.clone(), intensita);
+            if x == x2 && y == y2 {
+                break;
+            }
+            let doppio = 2 * errore;
+            if doppio >= dy {
+                errore += dy;
+                x += sx;
+            }
+            if doppio <= dx {
+                errore += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Colora le piastrelle della circonferenza di centro (`x`, `y`) e raggio `raggio`,
+    /// approssimata con l'algoritmo del punto medio, come `colora`, modificando il Piano
+    ///
+    /// # Arguments
+    /// * `x`, `y` - centro della circonferenza
+    /// * `raggio` - raggio della circonferenza, con raggio 0 viene colorato solo il centro;
+    ///   le piastrelle con coordinate fuori dagli i32 sono ignorate
+    /// * `colore` - colore delle piastrelle
+    /// * `intensita` - intensità delle piastrelle
+    ///
+    /// # Panics
+    /// * se `colore` o `intensita` non sono validi (vedi `colora`)
+    /// * se `raggio` è maggiore di `i32::MAX`
+    fn colora_cerchio(&mut self, x: i32, y: i32, raggio: u32, colore: String, intensita: u32) {
+        assert!(raggio <= i32::MAX as u32, "raggio non valido");
+
+        // in i64 l'errore e le coordinate dei punti non traboccano
+        let mut dx = raggio as i64;
+        let mut dy = 0;
+        let mut errore = 1 - dx;
+
+        while dx >= dy {
+            // gli 8 ottanti simmetrici
+            for (px, py) in [
+                (dx, dy),
+                (dy, dx),
+                (-dy, dx),
+                (-dx, dy),
+                (-dx, -dy),
+                (-dy, -dx),
+                (dy, -dx),
+                (dx, -dy),
+            ] {
+                let (Ok(px), Ok(py)) = (i32::try_from(x as i64 + px), i32::try_from(y as i64 + py))
+                else {
+                    continue;
+                };
+                self.colora(px, py, colore.clone(), intensita);
+            }
+
+            dy += 1;
+            if errore < 0 {
+                errore += 2 * dy + 1;
+            } else {
+                dx -= 1;
+                errore += 2 * (dy - dx) + 1;
+            }
+        }
+    }
+
+    /// Colora tutte le piastrelle del blocco **omogeneo** della piastrella (`x`, `y`),
+    /// come `colora`, modificando il Piano. Se la piastrella è spenta non fa nulla
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella
+    /// * `y` - ordinate della piastrella
+    /// * `colore` - colore delle piastrelle
+    /// * `intensita` - intensità delle piastrelle
+    ///
+    /// # Panics
+    /// * se `colore` o `intensita` non sono validi (vedi `colora`)
+    fn riempi(&mut self, x: i32, y: i32, colore: String, intensita: u32) {
+        let (.., blocco) = self._blocco_generico(x, y, true);
+        for Piastrella { x, y } in blocco {
+            self.colora(x, y, colore.clone(), intensita);
+        }
+    }
+
+    /// Restituisce lo stato (colorazione) di una piastrella, indicata da `x` e `y`
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella
+    /// * `y` - ordinate della piastrella
+    ///
+    /// # Returns
+    /// * `Some(Colorazione)` - se la piastrella è accesa, la sua colorazione
+    /// * `None` - se la piastrella è spenta
+    fn stato(&self, x: i32, y: i32) -> Option<Colorazione> {
+        self.piastrelle
+            .get(&Piastrella { x, y })
+            .map(|tinta| self._colorazione(tinta))
+    }
+
+    /// Restituisce la colorazione, con il nome del colore, di una tinta del piano
+    fn _colorazione(&self, Tinta { colore, intensita }: &Tinta) -> Colorazione {
+        Colorazione {
+            colore: String::from(self.tavolozza.nome(*colore)),
+            intensita: *intensita,
+        }
+    }
+
+    /// Restituisce le colorazioni di tutte le piastrelle accese, con i nomi dei colori
+    #[cfg(test)]
+    fn colorazioni(&self) -> HashMap<Piastrella, Colorazione> {
+        self.piastrelle
+            .iter()
+            .map(|(piastrella, tinta)| (piastrella, self._colorazione(tinta)))
+            .collect()
+    }
+
+    /// Riepiloga le piastrelle accese del rettangolo con vertici opposti (`x1`, `y1`) e
+    /// (`x2`, `y2`), estremi inclusi, con l'indice spaziale: il costo dipende dal perimetro
+    /// del rettangolo, non dalla sua area
+    ///
+    /// # Arguments
+    /// * `x1`, `y1` - vertice del rettangolo
+    /// * `x2`, `y2` - vertice opposto del rettangolo
+    ///
+    /// # Returns
+    /// * numero, intensità totale e colori delle piastrelle accese nel rettangolo
+    fn riepiloga_rettangolo(&self, x1: i32, y1: i32, x2: i32, y2: i32) -> Riepilogo {
+        self.spaziale.riepiloga(
+            &self.piastrelle,
+            (x1.min(x2), x1.max(x2)),
+            (y1.min(y2), y1.max(y2)),
+        )
+    }
+
+    /// Restituisce il numero di piastrelle accese nel rettangolo (vedi `riepiloga_rettangolo`)
+    fn conta_rettangolo(&self, x1: i32, y1: i32, x2: i32, y2: i32) -> usize {
+        self.riepiloga_rettangolo(x1, y1, x2, y2).piastrelle
+    }
+
+    /// Restituisce l'intensità totale delle piastrelle accese nel rettangolo
+    /// (vedi `riepiloga_rettangolo`)
+    fn intensita_rettangolo(&self, x1: i32, y1: i32, x2: i32, y2: i32) -> u64 {
+        self.riepiloga_rettangolo(x1, y1, x2, y2).intensita
+    }
+
+    /// Restituisce i colori delle piastrelle accese nel rettangolo
+    /// (vedi `riepiloga_rettangolo`)
+    ///
+    /// # Returns
+    /// * i colori presenti con il numero di piastrelle di ciascuno, in ordine alfabetico
+    fn istogramma_rettangolo(&self, x1: i32, y1: i32, x2: i32, y2: i32) -> Vec<(&str, usize)> {
+        let mut colori: Vec<(&str, usize)> = self
+            .riepiloga_rettangolo(x1, y1, x2, y2)
+            .colori
+            .into_iter()
+            .map(|(colore, n)| (self.tavolozza.nome(colore), n))
+            .collect();
+        colori.sort();
+        colori
+    }
+
+    /// Restituisce tutti i colori presenti nel piano, sia nelle piastrelle che nelle regole
+    ///
+    /// # Returns
+    /// * i colori, senza ripetizioni e in ordine alfabetico
+    fn colori(&self) -> Vec<String> {
+        let mut colori: Vec<String> = self
+            .piastrelle
+            .iter()
+            .map(|(_, Tinta { colore, .. })| colore)
+            .chain(self.regole.iter().flat_map(
+                |Regola {
+                     requisiti, colore, ..
+                 }| {
+                    requisiti
+                        .iter()
+                        .map(|Requisito { colore, .. }| colore)
+                        .chain([colore])
+                },
+            ))
+            .collect::<HashSet<&Colore>>()
+            .into_iter()
+            .map(|colore| String::from(self.tavolozza.nome(*colore)))
+            .collect();
+        colori.sort();
+        colori
+    }
+
+    /// Aggiunge una regola di colorazione (`Regola`) al piano, parsandola dalla
+    /// stringa `regola`. La stringa deve essere nel formato `colore coeff1 col1 coeff2 col2 ...`,
+    /// dove tutti i `coeff*` sono numerici e la loro somma non deve eccedere 8
+    /// il piano viene modificato
+    ///
+    /// # Arguments
+    /// * `regola` - stringa che rappresenta una regola
+    ///
+    /// # Panics
+    /// * se la regola non è valida (vedi `valida_regola`)
+    fn regola(&mut self, regola: String) {
+        let regola = self._parsa_regola(&regola);
+        self.regole.push(regola);
+        self._invalida_indice();
+    }
+
+    /// Valida una regola dalla stringa `regola`, nel formato
+    /// `colore coeff1 col1 coeff2 col2 ...`, **senza** aggiungerla al piano: né il piano
+    /// né la sua tavolozza sono modificati
+    ///
+    /// # Arguments
+    /// * `regola` - stringa che rappresenta una regola
+    ///
+    /// # Returns
+    /// * `Ok(())` - se la regola è valida
+    /// * `Err(String)` - la descrizione dell'errore se la regola è malformata (vedi `_leggi_regola`)
+    fn valida_regola(&self, regola: &str) -> Result<(), String> {
+        Self::_leggi_regola(regola).map(|_| ())
+    }
+
+    /// Parsa una regola dalla stringa `regola`, nel formato `colore coeff1 col1 ...`,
+    /// con i nomi dei colori
+    ///
+    /// # Returns
+    /// * `Ok((colore, requisiti))` - il colore della regola e i requisiti (coefficiente, colore)
+    /// * `Err(String)` - la descrizione dell'errore se la regola è malformata:
+    ///     * mancano del tutto i coefficienti
+    ///     * i coefficienti non sono accoppiati ad un colore
+    ///     * non tutti i coefficienti sono numerici
+    ///     * un coefficiente è nullo
+    ///     * un colore è ripetuto nei requisiti
+    ///     * la somma dei coefficienti eccede il numero di piastrelle adiacenti (8)
+    fn _leggi_regola(regola: &str) -> Result<TestoRegola<'_>, String> {
+        let parti: Vec<&str> = regola.split_whitespace().collect();
+
+        if parti.len() <= 2 {
+            return Err(String::from("regola invalida (mancanza coefficienti)"));
+        }
+        if parti.len().is_multiple_of(2) {
+            return Err(String::from("regola invalida (coppie malformate)"));
+        }
+
+        let mut requisiti: Vec<(u8, &str)> = Vec::new();
+        for (coefficiente, colore) in parti
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .zip(parti.iter().skip(2).step_by(2))
+        {
+            let coefficiente: u8 = coefficiente
+                .parse()
+                .map_err(|_| String::from("regola invalida (coefficiente invalido)"))?;
+
+            if coefficiente == 0 {
+                return Err(format!("regola invalida (coefficiente nullo per {colore})"));
+            }
+            if requisiti.iter().any(|(_, requisito)| requisito == colore) {
+                return Err(format!("regola invalida (colore {colore} ripetuto)"));
+            }
+
+            requisiti.push((coefficiente, colore));
+        }
+
+        let somma: u32 = requisiti
+            .iter()
+            .map(|(coefficiente, _)| *coefficiente as u32)
+            .sum();
+        if somma > ADIACENTI.len() as u32 {
+            return Err(format!(
+                "regola invalida (somma coefficienti {somma} maggiore di {})",
+                ADIACENTI.len()
+            ));
+        }
+
+        Ok((parti[0], requisiti))
+    }
+
+    /// Parsa una regola dalla stringa `regola` (vedi `valida_regola`), aggiungendo
+    /// i suoi colori alla tavolozza
+    ///
+    /// # Returns
+    /// * la regola parsata, con utilizzo 0
+    ///
+    /// # Panics
+    /// * se la regola non è valida, con la descrizione dell'errore
+    fn _parsa_regola(&mut self, regola: &str) -> Regola {
+        let (colore, requisiti) =
+            Self::_leggi_regola(regola).unwrap_or_else(|errore| panic!("{errore}"));
+        Regola {
+            requisiti: requisiti
+                .into_iter()
+                .map(|(coefficiente, colore)| Requisito {
+                    coefficiente,
+                    colore: self.tavolozza.interna(colore),
+                })
+                .collect(),
+            colore: self.tavolozza.interna(colore),
+            utilizzo: 0,
+        }
+    }
+
+    /// Rimuove la regola in posizione `indice`, modificando il piano
+    ///
+    /// # Arguments
+    /// * `indice` - posizione della regola da rimuovere
+    ///
+    /// # Returns
+    /// * `Some(Regola)` - la regola rimossa
+    /// * `None` - se non esiste una regola in posizione `indice`
+    fn rimuovi_regola(&mut self, indice: usize) -> Option<Regola> {
+        if indice >= self.regole.len() {
+            return None;
+        }
+        self._invalida_indice();
+        Some(self.regole.remove(indice))
+    }
+
+    /// Rimuove la *prima* regola uguale a `regola` (nel formato `colore coeff1 col1 ...`),
+    /// indipendentemente dal suo utilizzo, modificando il piano
+    ///
+    /// # Arguments
+    /// * `regola` - stringa che rappresenta la regola da rimuovere
+    ///
+    /// # Returns
+    /// * `Some(Regola)` - la regola rimossa
+    /// * `None` - se nessuna regola corrisponde
+    ///
+    /// # Panics
+    /// * se `regola` è malformata (vedi `regola`)
+    fn rimuovi_regola_testo(&mut self, regola: String) -> Option<Regola> {
+        let (colore, requisiti) =
+            Self::_leggi_regola(&regola).unwrap_or_else(|errore| panic!("{errore}"));
+
+        // una regola con un colore mai usato dal piano non può essere tra le sue regole
+        let colore = self.tavolozza.cerca(colore)?;
+        let requisiti: Vec<Requisito> = requisiti
+            .into_iter()
+            .map(|(coefficiente, colore)| {
+                Some(Requisito {
+                    coefficiente,
+                    colore: self.tavolozza.cerca(colore)?,
+                })
+            })
+            .collect::<Option<_>>()?;
+
+        let indice = self
+            .regole
+            .iter()
+            .position(|r| r.colore == colore && r.requisiti == requisiti)?;
+        self.rimuovi_regola(indice)
+    }
+
+    /// Sostituisce la regola in posizione `indice` con `regola`, mantenendo
+    /// l'utilizzo della regola sostituita, modificando il piano
+    ///
+    /// # Arguments
+    /// * `indice` - posizione della regola da sostituire
+    /// * `regola` - stringa che rappresenta la nuova regola
+    ///
+    /// # Returns
+    /// * `true` - se la regola è stata sostituita
+    /// * `false` - se non esiste una regola in posizione `indice`
+    ///
+    /// # Panics
+    /// * se `regola` è malformata (vedi `regola`)
+    fn sostituisci_regola(&mut self, indice: usize, regola: String) -> bool {
+        if indice >= self.regole.len() {
+            // valida comunque la regola, senza aggiungerne i colori alla tavolozza
+            Self::_leggi_regola(&regola).unwrap_or_else(|errore| panic!("{errore}"));
+            return false;
+        }
+        let nuova = self._parsa_regola(&regola);
+
+        let vecchia = &mut self.regole[indice];
+        *vecchia = Regola {
+            utilizzo: vecchia.utilizzo,
+            ..nuova
+        };
+        self._invalida_indice();
+        true
+    }
+
+    /// Azzera l'utilizzo di tutte le regole, modificando il piano
+    fn azzera_utilizzo(&mut self) {
+        self.regole
+            .iter_mut()
+            .for_each(|Regola { utilizzo, .. }| *utilizzo = 0);
+    }
+
+    /// Analizza staticamente le regole del piano, individuando le regole che non
+    /// potranno mai essere applicate:
+    /// - duplicate: uguali (colore e requisiti, in qualsiasi ordine) ad una regola precedente
+    /// - oscurate: una regola precedente ha requisiti meno stringenti, quindi è sempre
+    ///   applicabile quando lo è questa, e viene scelta prima
+    ///
+    /// Le regole che richiedono più piastrelle di quelle adiacenti sono già rifiutate
+    /// da `valida_regola`
+    ///
+    /// # Returns
+    /// * le anomalie trovate, in ordine di posizione della regola
+    fn analizza_regole(&self) -> Vec<Anomalia> {
+        let requisiti: Vec<HashMap<Colore, u8>> = self
+            .regole
+            .iter()
+            .map(|Regola { requisiti, .. }| {
+                requisiti
+                    .iter()
+                    .map(|r| (r.colore, r.coefficiente))
+                    .collect()
+            })
+            .collect();
+
+        // una regola `generale` copre una regola `specifica` se ogni suo requisito
+        // è soddisfatto da quelli di `specifica`
+        let copre = |generale: &HashMap<Colore, u8>, specifica: &HashMap<Colore, u8>| {
+            generale
+                .iter()
+                .all(|(colore, coeff)| specifica.get(colore).is_some_and(|c| c >= coeff))
+        };
+
+        let mut anomalie = Vec::new();
+        for (j, regola) in self.regole.iter().enumerate() {
+            let duplicata = (0..j)
+                .find(|&i| requisiti[i] == requisiti[j] && self.regole[i].colore == regola.colore);
+            if let Some(originale) = duplicata {
+                anomalie.push(Anomalia::Duplicata {
+                    indice: j,
+                    originale,
+                });
+            } else if let Some(da) = (0..j).find(|&i| copre(&requisiti[i], &requisiti[j])) {
+                anomalie.push(Anomalia::Oscurata { indice: j, da });
+            }
+        }
+        anomalie
+    }
+
+    /// Restituisce le anomalie delle regole (vedi `analizza_regole`) nel formato
+    /// ```format
+    /// (
+    /// indice duplicata di originale
+    /// indice oscurata da altra
+    /// ...
+    /// )
+    /// ```
+    ///
+    /// # Returns
+    /// * `String` che rappresenta le anomalie nel formato descritto
+    fn stampa_analisi(&self) -> String {
+        let mut result = String::from("(\n");
+        self.analizza_regole().iter().for_each(|anomalia| {
+            result.push_str(&format!("{anomalia}\n"));
+        });
+        result.push(')');
+        result
+    }
+
+    /// Restituisce le regole di propagazione (`Regole`) contenute nel piano nel formato
+    /// ```format
+    /// (
+    /// colore: coeff1 col1 coeff2 col2 ...
+    /// colore: coeff1 col1 coeff2 col2 coeff3 col3
+    /// ...
+    /// )
+    /// ```
+    ///
+    /// # Returns
+    /// * `String` che rappresenta le regole nel formato descritto
+    fn stampa(&self) -> String {
+        let mut result = String::from("(\n");
+        self.regole.iter().for_each(|regola| {
+            result.push_str(&format!("{}\n", self.tavolozza.nomina(regola)));
+        });
+        result.push(')');
+        result
+    }
+
+    /// Restituisce le regole di propagazione (`Regole`) contenute nel piano, ciascuna
+    /// preceduta dalla sua posizione e dal suo utilizzo, nel formato
+    /// ```format
+    /// (
+    /// 0 utilizzo colore: coeff1 col1 coeff2 col2 ...
+    /// 1 utilizzo colore: coeff1 col1 coeff2 col2 coeff3 col3
+    /// ...
+    /// )
+    /// ```
+    ///
+    /// # Returns
+    /// * `String` che rappresenta le regole nel formato descritto
+    fn stampa_utilizzo(&self) -> String {
+        let mut result = String::from("(\n");
+        self.regole.iter().enumerate().for_each(|(i, regola)| {
+            result.push_str(&format!(
+                "{i} {} {}\n",
+                regola.utilizzo,
+                self.tavolozza.nomina(regola)
+            ));
+        });
+        result.push(')');
+        result
+    }
+
+    /// Restituisce l'intensità totale e le piastrelle nel blocco della piastrella
+    /// individuata da `x` e `y`
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella
+    /// * `y` - ordinate della piastrella
+    /// * `omogeneo` - se il blocco deve essere omogeneo
+    ///
+    /// # Returns
+    /// * l'intensità totale e l'insieme delle piastrelle appartenenti al blocco
+    ///   se la piastrella `x`, `y` è accesa
+    /// * `0` e l'insieme vuoto se la piastrella `x`, `y` è spenta
+    fn _blocco_generico(&self, x: i32, y: i32, omogeneo: bool) -> (u32, HashSet<Piastrella>) {
+        let start = Piastrella { x, y };
+
+        let Tinta {
+            colore: colore_omogeneo,
+            intensita: mut totale, // inizializza totale a intensità di (x,y)
+        } = match self.piastrelle.get(&start) {
+            Some(colorazione) => colorazione,
+            None => return (0, HashSet::new()),
+        };
+
+        let mut coda = VecDeque::from([start.clone()]);
+        let mut visitati = HashSet::from([start.clone()]);
+
+        while let Some(Piastrella { x: cx, y: cy }) = coda.pop_front() {
+            for (dx, dy) in ADIACENTI {
+                let adiacente = Piastrella {
+                    x: cx + dx,
+                    y: cy + dy,
+                };
+
+                if visitati.contains(&adiacente) {
+                    continue;
+                }
+
+                if let Some(Tinta { colore, intensita }) = self.piastrelle.get(&adiacente) {
+                    if omogeneo && colore != colore_omogeneo {
+                        continue;
+                    }
+
+                    visitati.insert(adiacente.clone());
+                    coda.push_back(adiacente.clone());
+                    totale += intensita;
+                }
+            }
+        }
+
+        (totale, visitati)
+    }
+
+    /// Restituisce l'intensità totale della piastrelle nel blocco **non** omogeneo
+    /// della piastrella individuata da `x` e `y`
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella
+    /// * `y` - ordinate della piastrella
+    ///
+    /// # Returns
+    /// * l'intensità totale se la piastrella `x`, `y` è accesa
+    /// * `0` se la piastrella `x`, `y` è spenta
+    fn blocco(&self, x: i32, y: i32) -> u32 {
+        let (totale, ..) = self._blocco_generico(x, y, false);
+        totale
+    }
+
+    /// Restituisce l'intensità totale della piastrelle nel blocco **omogeneo**
+    /// della piastrella individuata da `x` e `y`
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella
+    /// * `y` - ordinate della piastrella
+    ///
+    /// # Returns
+    /// * l'intensità totale se la piastrella `x`, `y` è accesa
+    /// * `0` se la piastrella `x`, `y` è spenta
+    fn blocco_omogeneo(&self, x: i32, y: i32) -> u32 {
+        let (totale, ..) = self._blocco_generico(x, y, true);
+        totale
+    }
+
+    /// Etichetta i blocchi dell'intero piano: ogni piastrella accesa riceve il numero
+    /// del suo blocco, con i blocchi numerati da 0 nell'ordine della loro prima piastrella
+    /// per ascisse e ordinate. Le piastrelle ordinate sono divise in porzioni tra i thread
+    /// del piano: ogni thread unisce le piastrelle adiacenti della sua porzione, poi i
+    /// blocchi che attraversano più porzioni sono uniti in sequenza, quindi le etichette
+    /// **non** dipendono dal numero di thread
+    ///
+    /// # Arguments
+    /// * `omogeneo` - se i blocchi devono essere omogenei
+    ///
+    /// # Returns
+    /// * le piastrelle accese ordinate per ascisse e ordinate, con la loro tinta e
+    ///   l'etichetta del loro blocco
+    fn etichetta_blocchi(&self, omogeneo: bool) -> Vec<(Piastrella, Tinta, usize)> {
+        let mut piastrelle: Vec<(Piastrella, Tinta)> = self
+            .piastrelle
+            .iter()
+            .map(|(piastrella, tinta)| (piastrella, *tinta))
+            .collect();
+        piastrelle.sort_by_key(|(piastrella, _)| (piastrella.x, piastrella.y));
+        let posizione = |x: i32, y: i32| {
+            piastrelle
+                .binary_search_by_key(&(x, y), |(piastrella, _)| (piastrella.x, piastrella.y))
+                .ok()
+        };
+
+        // per ogni porzione: il rappresentante (globale) di ogni piastrella nella porzione
+        // e gli archi verso le piastrelle delle porzioni successive
+        let porzioni = parallelo::per_porzioni(&piastrelle, self.thread, |inizio, porzione| {
+            let fine = inizio + porzione.len();
+            let mut genitori: Vec<usize> = (0..porzione.len()).collect();
+            let mut archi: Vec<(usize, usize)> = Vec::new();
+            for (i, (Piastrella { x, y }, tinta)) in porzione.iter().enumerate() {
+                for (dx, dy) in ADIACENTI {
+                    let Some(j) = posizione(x + dx, y + dy) else {
+                        continue;
+                    };
+                    if omogeneo && piastrelle[j].1.colore != tinta.colore {
+                        continue;
+                    }
+                    if (inizio..fine).contains(&j) {
+                        parallelo::unisci(&mut genitori, i, j - inizio);
+                    } else if j >= fine {
+                        // gli archi verso le porzioni precedenti li registrano quelle
+                        archi.push((inizio + i, j));
+                    }
+                }
+            }
+            let radici: Vec<usize> = (0..porzione.len())
+                .map(|i| inizio + parallelo::trova(&mut genitori, i))
+                .collect();
+            (radici, archi)
+        });
+
+        // unisce i blocchi tra le porzioni
+        let mut genitori: Vec<usize> = Vec::with_capacity(piastrelle.len());
+        let mut archi: Vec<(usize, usize)> = Vec::new();
+        for (radici, archi_porzione) in porzioni {
+            genitori.extend(radici);
+            archi.extend(archi_porzione);
+        }
+        for (i, j) in archi {
+            parallelo::unisci(&mut genitori, i, j);
+        }
+
+        // numera i blocchi in ordine di prima piastrella
+        let mut etichette: Vec<Option<usize>> = vec![None; piastrelle.len()];
+        let mut blocchi = 0;
+        let mut result = Vec::with_capacity(piastrelle.len());
+        for (i, (piastrella, tinta)) in piastrelle.iter().enumerate() {
+            let radice = parallelo::trova(&mut genitori, i);
+            let etichetta = *etichette[radice].get_or_insert_with(|| {
+                blocchi += 1;
+                blocchi - 1
+            });
+            result.push((piastrella.clone(), *tinta, etichetta));
+        }
+        result
+    }
+
+    /// Restituisce i blocchi dell'intero piano (vedi `etichetta_blocchi`) nel formato
+    /// ```format
+    /// (
+    /// x y piastrelle intensita
+    /// ...
+    /// )
+    /// ```
+    /// con una riga per blocco, dove `x`, `y` è la prima piastrella del blocco per
+    /// ascisse e ordinate, in ordine di etichetta
+    ///
+    /// # Arguments
+    /// * `omogeneo` - se i blocchi devono essere omogenei
+    ///
+    /// # Returns
+    /// * `String` che rappresenta i blocchi nel formato descritto
+    fn stampa_blocchi(&self, omogeneo: bool) -> String {
+        // prima piastrella, numero di piastrelle e intensità totale di ogni blocco
+        let mut blocchi: Vec<(Piastrella, usize, u32)> = Vec::new();
+        for (piastrella, tinta, etichetta) in self.etichetta_blocchi(omogeneo) {
+            match blocchi.get_mut(etichetta) {
+                Some((_, piastrelle, totale)) => {
+                    *piastrelle += 1;
+                    *totale += tinta.intensita;
+                }
+                None => blocchi.push((piastrella, 1, tinta.intensita)),
+            }
+        }
+
+        let mut result = String::from("(\n");
+        for (Piastrella { x, y }, piastrelle, totale) in blocchi {
+            result.push_str(&format!("{x} {y} {piastrelle} {totale}\n"));
+        }
+        result.push(')');
+        result
+    }
+
+    /// Conta i colori delle piastrelle accese adiacenti alla piastrella individuata
+    /// da `x`, `y`
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella
+    /// * `y` - ordinate della piastrella
+    ///
+    /// # Returns
+    /// * per ogni colore presente nell'intorno, il numero di piastrelle adiacenti di quel colore
+    fn _intorno(&self, x: i32, y: i32) -> Intorno {
+        let mut intorno = Intorno::default();
+
+        ADIACENTI
+            .iter()
+            .map(|(dx, dy)| Piastrella {
+                x: x + dx,
+                y: y + dy,
+            })
+            .filter_map(|adiacente| self.piastrelle.get(&adiacente))
+            .for_each(|Tinta { colore, .. }| intorno.aggiungi(*colore));
+
+        intorno
+    }
+
+    /// Restituisce l'indice delle regole, costruendolo se le regole sono cambiate
+    fn _indice(&self) -> &IndiceRegole {
+        self.indice.get_or_init(|| IndiceRegole::new(&self.regole))
+    }
+
+    /// Scarta l'indice delle regole, da chiamare ad ogni modifica delle regole
+    /// (diversa dall'utilizzo)
+    fn _invalida_indice(&mut self) {
+        self.indice.take();
+    }
+
+    /// Controlla se esiste una regola di propagazione applicabile alla piastrella
+    /// individuata da `x`, `y`, **senza** applicarla (il piano **non** è modificato)
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella da propagare
+    /// * `y` - ordinate della piastrella da propagare
+    ///
+    /// # Returns
+    /// * `Some(indice, colore)` - l'indice della prima regola applicabile e il colore finale
+    /// * `None` - se nessuna regola è applicabile
+    fn _simula_propagazione(&self, x: i32, y: i32) -> Option<(usize, Colore)> {
+        // "precalcola" valori disponibili nell'intorno di (x,y)
+        let intorno = self._intorno(x, y);
+
+        // trova prima regola applicabile
+        let i = self._indice().prima_applicabile(&self.regole, &intorno)?;
+        Some((i, self.regole[i].colore))
+    }
+
+    /// Spiega la propagazione della piastrella individuata da `x`, `y`, **senza**
+    /// applicarla (il piano **non** è modificato), nel formato
+    /// ```format
+    /// (
+    /// intorno: col1 n1 col2 n2 ...
+    /// 0 colore: coeff1 col1 ... -> manca coeff col (presenti n)
+    /// 1 colore: coeff1 col1 ... -> applicabile
+    /// ...
+    /// risultato: indice colore
+    /// )
+    /// ```
+    /// dove i colori dell'intorno sono in ordine alfabetico e `risultato` è la prima
+    /// regola applicabile (quella che userebbe `propaga`), o `nessuna` se nessuna lo è
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella
+    /// * `y` - ordinate della piastrella
+    ///
+    /// # Returns
+    /// * `String` che rappresenta la spiegazione nel formato descritto
+    fn spiega(&self, x: i32, y: i32) -> String {
+        let intorno = self._intorno(x, y);
+
+        let mut colori: Vec<(&str, &u8)> = intorno
+            .iter()
+            .map(|(colore, n)| (self.tavolozza.nome(*colore), n))
+            .collect();
+        colori.sort();
+
+        let mut result = String::from("(\nintorno:");
+        colori
+            .iter()
+            .for_each(|(colore, n)| result.push_str(&format!(" {colore} {n}")));
+        result.push('\n');
+
+        self.regole.iter().enumerate().for_each(|(i, regola)| {
+            match intorno.requisito_mancante(regola) {
+                Some(Requisito {
+                    coefficiente,
+                    colore,
+                }) => result.push_str(&format!(
+                    "{i} {} -> manca {coefficiente} {} (presenti {})\n",
+                    self.tavolozza.nomina(regola),
+                    self.tavolozza.nome(*colore),
+                    intorno.conta(*colore)
+                )),
+                None => result.push_str(&format!(
+                    "{i} {} -> applicabile\n",
+                    self.tavolozza.nomina(regola)
+                )),
+            }
+        });
+
+        match self._simula_propagazione(x, y) {
+            Some((i, colore)) => {
+                result.push_str(&format!("risultato: {i} {}\n", self.tavolozza.nome(colore)))
+            }
+            None => result.push_str("risultato: nessuna\n"),
+        }
+        result.push(')');
+        result
+    }
+
+    /// Propaga una piastrella, applicando la *prima* regola applicabile, modifica
+    /// il piano senza restituire nulla
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella da propagare
+    /// * `y` - ordinate della piastrella da propagare
+    fn propaga(&mut self, x: i32, y: i32) {
+        // se una regola è stata trovata applicabile
+        if let Some((i, colore)) = self._simula_propagazione(x, y) {
+            // intensità invariata se accesa o 1
+            let intensita = *self
+                .piastrelle
+                .get(&Piastrella { x, y })
+                .map(|Tinta { intensita, .. }| intensita)
+                .unwrap_or(&1);
+
+            self._accendi(Piastrella { x, y }, Tinta { colore, intensita });
+            self.regole[i].utilizzo += 1;
+        }
+    }
+
+    /// Calcola la propagazione di un blocco (vedi `propaga_blocco`) **senza** applicarla:
+    /// il piano e l'utilizzo delle regole **non** sono modificati
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella da propagare
+    /// * `y` - ordinate della piastrella da propagare
+    ///
+    /// # Returns
+    /// * le applicazioni che `propaga_blocco` effettuerebbe, ordinate per ascisse e ordinate
+    fn anteprima_blocco(&self, x: i32, y: i32) -> Vec<Applicazione> {
+        // calcola blocco di (x,y)
+        let (.., blocco) = self._blocco_generico(x, y, false);
+        let mut blocco: Vec<Piastrella> = blocco.into_iter().collect();
+        blocco.sort_by_key(|piastrella| (piastrella.x, piastrella.y));
+
+        // costruisce l'indice prima di dividere il lavoro tra i thread
+        self._indice();
+
+        // trova la regola applicabile ad ogni piastrella del blocco, a porzioni ordinate
+        // in parallelo: concatenate, le applicazioni restano ordinate
+        parallelo::per_porzioni(&blocco, self.thread, |_, porzione| {
+            porzione
+                .iter()
+                .filter_map(|piastrella| {
+                    let (regola, colore) = self._simula_propagazione(piastrella.x, piastrella.y)?;
+                    let colore_precedente = self.piastrelle.get(piastrella)?.colore;
+                    Some(Applicazione {
+                        piastrella: piastrella.clone(),
+                        colore_precedente,
+                        colore,
+                        regola,
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Restituisce l'anteprima della propagazione di un blocco (vedi `anteprima_blocco`) nel formato
+    /// ```format
+    /// (
+    /// x y colore_precedente colore regola
+    /// ...
+    /// )
+    /// ```
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella da propagare
+    /// * `y` - ordinate della piastrella da propagare
+    ///
+    /// # Returns
+    /// * `String` che rappresenta le applicazioni nel formato descritto
+    fn stampa_anteprima_blocco(&self, x: i32, y: i32) -> String {
+        let mut result = String::from("(\n");
+        self.anteprima_blocco(x, y).iter().for_each(|applicazione| {
+            result.push_str(&format!("{}\n", self.tavolozza.nomina(applicazione)));
+        });
+        result.push(')');
+        result
+    }
+
+    /// Propaga un blocco, applicando a ciascuna piastrella del blocco la *prima*
+    /// regola applicabile. I cambiamenti non sono applicati fino alla *fine* di
+    /// tutte le operazioni, ovvero la propagazione di una piastrella del blocco
+    /// **non** può far scattare la propagazione di un'altra piastrella nello stesso blocco.
+    /// Modifica il piano senza restituire nulla
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella da propagare
+    /// * `y` - ordinate della piastrella da propagare
+    fn propaga_blocco(&mut self, x: i32, y: i32) {
+        let applicazioni = self.anteprima_blocco(x, y);
+
+        // applica le regole, le piastrelle del blocco sono tutte accese
+        for Applicazione {
+            piastrella,
+            colore,
+            regola,
+            ..
+        } in applicazioni
+        {
+            if let Some(&tinta) = self.piastrelle.get(&piastrella) {
+                self._accendi(piastrella, Tinta { colore, ..tinta });
+            }
+            self.regole[regola].utilizzo += 1;
+        }
+    }
+
+    /// Restituisce le piastrelle del blocco della piastrella individuata da `x`, `y`
+    /// nell'ordine di visita `ordine`
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella
+    /// * `y` - ordinate della piastrella
+    /// * `ordine` - ordine di visita delle piastrelle del blocco
+    ///
+    /// # Returns
+    /// * le piastrelle del blocco nell'ordine richiesto, vuoto se la piastrella `x`, `y` è spenta
+    fn _ordina_blocco(&self, x: i32, y: i32, ordine: Ordine) -> Vec<Piastrella> {
+        let (.., blocco) = self._blocco_generico(x, y, false);
+
+        match ordine {
+            Ordine::Bfs => {
+                if blocco.is_empty() {
+                    return Vec::new();
+                }
+
+                let start = Piastrella { x, y };
+                let mut visita = vec![start.clone()];
+                let mut visitati = HashSet::from([start]);
+
+                let mut i = 0;
+                while i < visita.len() {
+                    let Piastrella { x: cx, y: cy } = visita[i];
+                    for (dx, dy) in ADIACENTI {
+                        let adiacente = Piastrella {
+                            x: cx + dx,
+                            y: cy + dy,
+                        };
+                        if blocco.contains(&adiacente) && visitati.insert(adiacente.clone()) {
+                            visita.push(adiacente);
+                        }
+                    }
+                    i += 1;
+                }
+                visita
+            }
+            Ordine::Righe => {
+                let mut visita: Vec<Piastrella> = blocco.into_iter().collect();
+                visita.sort_by_key(|Piastrella { x, y }| (*y, *x));
+                visita
+            }
+            Ordine::Coordinate => {
+                let mut visita: Vec<Piastrella> = blocco.into_iter().collect();
+                visita.sort_by_key(|Piastrella { x, y }| (*x, *y));
+                visita
+            }
+        }
+    }
+
+    /// Propaga un blocco in maniera *sequenziale*, applicando a ciascuna piastrella
+    /// del blocco, nell'ordine `ordine`, la *prima* regola applicabile. A differenza di
+    /// `propaga_blocco` ogni cambiamento è applicato subito, ovvero la propagazione di una
+    /// piastrella del blocco **può** far scattare la propagazione delle piastrelle visitate
+    /// dopo. Modifica il piano senza restituire nulla
+    ///
+    /// # Arguments
+    /// * `x` - ascisse della piastrella da propagare
+    /// * `y` - ordinate della piastrella da propagare
+    /// * `ordine` - ordine di visita delle piastrelle del blocco
+    fn propaga_blocco_sequenziale(&mut self, x: i32, y: i32, ordine: Ordine) {
+        for Piastrella { x, y } in self._ordina_blocco(x, y, ordine) {
+            self.propaga(x, y);
+        }
+    }
+
+    /// Ordina le regole di propagazione, in base al loro consumo (in maniera crescente).
+    /// Due regole con consumo uguale rimangono nello stesso ordine relativo (stabile).
+    /// Modifica il piano senza restituire nulla
+    fn ordina(&mut self) {
+        self.regole.sort_by_key(|Regola { utilizzo, .. }| *utilizzo);
+        self._invalida_indice();
+    }
+
+    fn pista(&self, x: i32, y: i32, s: String) -> Option<u32> {
+        let mut cx = x;
+        let mut cy = y;
+
+        let mut totale_intensita: u32 = match self.piastrelle.get(&Piastrella { x, y }) {
+            Some(Tinta { intensita, .. }) => *intensita,
+            None => return None,
+        };
+
+        for dir in s.split_whitespace() {
+            match dir {
+                "NN" => (cx += 0, cy += 1),
+                "SS" => (cx += 0, cy += -1),
+                "EE" => (cx += 1, cy += 0),
+                "WW" => (cx += -1, cy += 0),
+                "NE" => (cx += 1, cy += 1),
+                "NW" => (cx += -1, cy += 1),
+                "SE" => (cx += 1, cy += -1),
+                "SW" => (cx += -1, cy += -1),
+                _ => return None,
+            };
+
+            match self.piastrelle.get(&Piastrella { x: cx, y: cy }) {
+                Some(Tinta { intensita, .. }) => totale_intensita += intensita,
+                None => return None,
+            }
+        }
+
+        Some(totale_intensita)
+    }
+
+    fn lung(&self, x1: i32, y1: i32, x2: i32, y2: i32) -> Option<u32> {
+        let start_dist = match self.piastrelle.get(&Piastrella { x: x1, y: y1 }) {
+            Some(Tinta { intensita, .. }) => intensita,
+            None => return None,
+        };
+
+        if x1 == x2 && y1 == y2 {
+            return Some(*start_dist);
+        }
+
+        let mut coda = BinaryHeap::from([Reverse((*start_dist, x1, y1))]);
+        let mut visitati: HashSet<Piastrella> = HashSet::from([Piastrella { x: x1, y: y1 }]);
+
+        while !coda.is_empty() {
+            let (dist, cx, cy) = coda.pop().unwrap().0;
+
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let adiacente = Piastrella {
+                        x: cx + dx,
+                        y: cy + dy,
+                    };
+
+                    if visitati.contains(&adiacente) {
+                        continue;
+                    }
+
+                    if let Some(Tinta { intensita, .. }) = self.piastrelle.get(&adiacente) {
+                        if cx + dx == x2 && cy + dy == y2 {
+                            return Some(dist + intensita);
+                        }
+
+                        visitati.insert(adiacente.clone());
+                        coda.push(Reverse((dist + intensita, cx + dx, cy + dy)));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Esito dell'esecuzione di un comando
+#[derive(PartialEq, Debug)]
+enum Esito {
+    /// il comando è stato eseguito, si prosegue con il successivo
+    Continua,
+    /// il comando richiede di terminare l'esecuzione (`q`)
+    Termina,
+}
+
+/// Livello di tracciamento dell'output di `run`, per diagnosticare le differenze
+/// rispetto ad un output atteso
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Traccia {
+    /// solo l'output dei comandi
+    Nessuna,
+    /// ogni riga di output preceduta dal numero della riga di input e dal suo comando
+    Righe,
+    /// come `Righe`, riportando anche ogni piastrella modificata (prima e dopo il comando)
+    Mutazioni,
+}
+
+/// Formato dell'output dei comandi
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Formato {
+    /// righe di testo, come da specifica del progetto
+    Testo,
+    /// un oggetto JSON per riga (JSON lines)
+    Json,
+}
+
+/// Comando accettato da `esegui`, nella forma breve (una lettera) o estesa (nome)
+struct Comando {
+    lettera: &'static str,
+    nome: &'static str,
+    argomenti: &'static str,
+    descrizione: &'static str,
+}
+
+/// Tabella dei comandi accettati da `esegui`, usata sia per il parsing che per l'aiuto
+const COMANDI: [Comando; 35] = [
+    Comando {
+        lettera: "C",
+        nome: "colora",
+        argomenti: "x y colore i",
+        descrizione: "colora la piastrella (x,y) con colore e intensità i",
+    },
+    Comando {
+        lettera: "S",
+        nome: "spegni",
+        argomenti: "x y",
+        descrizione: "spegne la piastrella (x,y)",
+    },
+    Comando {
+        lettera: "F",
+        nome: "colora_rettangolo",
+        argomenti: "x1 y1 x2 y2 colore i",
+        descrizione: "colora il rettangolo con vertici opposti (x1,y1) e (x2,y2)",
+    },
+    Comando {
+        lettera: "X",
+        nome: "spegni_rettangolo",
+        argomenti: "x1 y1 x2 y2",
+        descrizione: "spegne il rettangolo con vertici opposti (x1,y1) e (x2,y2)",
+    },
+    Comando {
+        lettera: "l",
+        nome: "colora_linea",
+        argomenti: "x1 y1 x2 y2 colore i",
+        descrizione: "colora il segmento da (x1,y1) a (x2,y2)",
+    },
+    Comando {
+        lettera: "c",
+        nome: "colora_cerchio",
+        argomenti: "x y raggio colore i",
+        descrizione: "colora la circonferenza di centro (x,y)",
+    },
+    Comando {
+        lettera: "f",
+        nome: "riempi",
+        argomenti: "x y colore i",
+        descrizione: "colora il blocco omogeneo di (x,y)",
+    },
+    Comando {
+        lettera: "I",
+        nome: "importa",
+        argomenti: "file x y [i]",
+        descrizione: "importa una griglia con legenda o un'immagine PPM (intensità i) in (x,y)",
+    },
+    Comando {
+        lettera: "K",
+        nome: "carica",
+        argomenti: "piastrelle|regole file",
+        descrizione: "carica le piastrelle da un file CSV o le regole da un file JSON",
+    },
+    Comando {
+        lettera: "E",
+        nome: "esporta",
+        argomenti: "piastrelle|regole [file]",
+        descrizione: "esporta le piastrelle in CSV o le regole in JSON, nel file o in output",
+    },
+    Comando {
+        lettera: "r",
+        nome: "regola",
+        argomenti: "colore k1 c1 k2 c2 ...",
+        descrizione: "aggiunge una regola di propagazione",
+    },
+    Comando {
+        lettera: "?",
+        nome: "stato",
+        argomenti: "x y",
+        descrizione: "stampa colore e intensità della piastrella (x,y), se accesa",
+    },
+    Comando {
+        lettera: "s",
+        nome: "stampa",
+        argomenti: "",
+        descrizione: "stampa le regole di propagazione",
+    },
+    Comando {
+        lettera: "u",
+        nome: "stampa_utilizzo",
+        argomenti: "",
+        descrizione: "stampa le regole con posizione e utilizzo",
+    },
+    Comando {
+        lettera: "R",
+        nome: "rimuovi_regola",
+        argomenti: "i | regola",
+        descrizione: "rimuove la regola in posizione i, o uguale a regola",
+    },
+    Comando {
+        lettera: "M",
+        nome: "sostituisci_regola",
+        argomenti: "i regola",
+        descrizione: "sostituisce la regola in posizione i, mantenendo l'utilizzo",
+    },
+    Comando {
+        lettera: "Z",
+        nome: "azzera_utilizzo",
+        argomenti: "",
+        descrizione: "azzera l'utilizzo di tutte le regole",
+    },
+    Comando {
+        lettera: "a",
+        nome: "analizza",
+        argomenti: "",
+        descrizione: "stampa le regole duplicate o oscurate",
+    },
+    Comando {
+        lettera: "b",
+        nome: "blocco",
+        argomenti: "x y",
+        descrizione: "stampa l'intensità totale del blocco di (x,y)",
+    },
+    Comando {
+        lettera: "B",
+        nome: "blocco_omogeneo",
+        argomenti: "x y",
+        descrizione: "stampa l'intensità totale del blocco omogeneo di (x,y)",
+    },
+    Comando {
+        lettera: "n",
+        nome: "conta",
+        argomenti: "x1 y1 x2 y2",
+        descrizione: "stampa il numero di piastrelle accese nel rettangolo",
+    },
+    Comando {
+        lettera: "w",
+        nome: "somma",
+        argomenti: "x1 y1 x2 y2",
+        descrizione: "stampa l'intensità totale del rettangolo",
+    },
+    Comando {
+        lettera: "g",
+        nome: "istogramma",
+        argomenti: "x1 y1 x2 y2",
+        descrizione: "stampa i colori del rettangolo con il numero di piastrelle, o nessuno",
+    },
+    Comando {
+        lettera: "N",
+        nome: "blocchi",
+        argomenti: "[omogenei]",
+        descrizione: "stampa prima piastrella, piastrelle e intensità di ogni blocco",
+    },
+    Comando {
+        lettera: "e",
+        nome: "spiega",
+        argomenti: "x y",
+        descrizione: "spiega la propagazione della piastrella (x,y)",
+    },
+    Comando {
+        lettera: "v",
+        nome: "anteprima_blocco",
+        argomenti: "x y",
+        descrizione: "stampa l'anteprima della propagazione del blocco di (x,y)",
+    },
+    Comando {
+        lettera: "p",
+        nome: "propaga",
+        argomenti: "x y",
+        descrizione: "propaga la piastrella (x,y)",
+    },
+    Comando {
+        lettera: "P",
+        nome: "propaga_blocco",
+        argomenti: "x y",
+        descrizione: "propaga il blocco di (x,y)",
+    },
+    Comando {
+        lettera: "A",
+        nome: "propaga_sequenziale",
+        argomenti: "x y bfs|righe|coordinate",
+        descrizione: "propaga il blocco di (x,y) in maniera sequenziale",
+    },
+    Comando {
+        lettera: "o",
+        nome: "ordina",
+        argomenti: "",
+        descrizione: "ordina le regole per utilizzo crescente",
+    },
+    Comando {
+        lettera: "t",
+        nome: "pista",
+        argomenti: "x y d1 d2 ...",
+        descrizione: "stampa l'intensità della pista da (x,y) lungo le direzioni",
+    },
+    Comando {
+        lettera: "L",
+        nome: "lung",
+        argomenti: "x1 y1 x2 y2",
+        descrizione: "stampa la lunghezza della pista più breve tra due piastrelle",
+    },
+    Comando {
+        lettera: "T",
+        nome: "thread",
+        argomenti: "n",
+        descrizione: "usa n thread per anteprima/propagazione di un blocco e blocchi",
+    },
+    Comando {
+        lettera: "q",
+        nome: "esci",
+        argomenti: "",
+        descrizione: "termina",
+    },
+    Comando {
+        lettera: "h",
+        nome: "help",
+        argomenti: "",
+        descrizione: "stampa questo aiuto",
+    },
+];
+
+/// Cerca nella tabella `COMANDI` il comando con forma breve o estesa `nome`
+///
+/// # Returns
+/// * `Some(Comando)` - il comando corrispondente
+/// * `None` - se il comando non esiste
+fn cerca_comando(nome: &str) -> Option<&'static Comando> {
+    COMANDI
+        .iter()
+        .find(|comando| comando.lettera == nome || comando.nome == nome)
+}
+
+/// Restituisce l'aiuto dei comandi, una riga per comando nel formato
+/// `lettera nome argomenti - descrizione`, con le colonne allineate
+///
+/// # Returns
+/// * `String` che rappresenta l'aiuto nel formato descritto
+fn aiuto() -> String {
+    let nome = COMANDI.iter().map(|c| c.nome.len()).max().unwrap_or(0);
+    let argomenti = COMANDI.iter().map(|c| c.argomenti.len()).max().unwrap_or(0);
+
+    COMANDI
+        .iter()
+        .map(|comando| {
+            format!(
+                "{} {:nome$} {:argomenti$} - {}",
+                comando.lettera, comando.nome, comando.argomenti, comando.descrizione
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Divide una riga di input nelle sue parti: le parti sono separate da un numero
+/// qualsiasi di spazi o tabulazioni, tutto ciò che segue `#` è un commento e gli
+/// spazi (compreso `\r` delle righe terminate da `\r\n`) ai lati sono ignorati
+///
+/// # Arguments
+/// * `riga` - riga di input
+///
+/// # Returns
+/// * le parti della riga, vuoto se la riga è vuota o contiene solo un commento
+fn tokenizza(riga: &str) -> Vec<&str> {
+    let riga = match riga.find('#') {
+        Some(commento) => &riga[..commento],
+        None => riga,
+    };
+    riga.split_whitespace().collect()
+}
+
+/// Parsa un argomento di un comando
+///
+/// # Returns
+/// * `Ok(T)` - l'argomento parsato
+/// * `Err(String)` - `input non valido` se l'argomento non è parsabile
+fn argomento<T: std::str::FromStr>(argomento: &str) -> Result<T, String> {
+    argomento
+        .parse()
+        .map_err(|_| String::from("input non valido"))
+}
+
+/// Parsa l'argomento intensità di un comando
+///
+/// # Returns
+/// * `Ok(u32)` - l'intensità parsata
+/// * `Err(String)` - se l'argomento non è parsabile o l'intensità è nulla
+fn intensita(argomento: &str) -> Result<u32, String> {
+    match argomento.parse() {
+        Ok(0) => Err(String::from("intensità non valida")),
+        Ok(intensita) => Ok(intensita),
+        Err(..) => Err(String::from("input non valido")),
+    }
+}
+
+/// Controlla una condizione sugli argomenti di un comando
+///
+/// # Returns
+/// * `Err(String)` - `input non valido` se la condizione non è rispettata
+fn controlla(condizione: bool) -> Result<(), String> {
+    match condizione {
+        true => Ok(()),
+        false => Err(String::from("input non valido")),
+    }
+}
+
+/// Esegue sul piano il comando contenuto in `riga`, passando gli eventuali risultati a `logger`
+/// nel formato `formato`. Il comando può essere indicato sia nella forma breve che in quella
+/// estesa (vedi `COMANDI`), le righe vuote o di solo commento sono ignorate (vedi `tokenizza`).
+/// In formato `Json` ogni comando che produce output (anche nullo, come `?` su una piastrella
+/// spenta) produce esattamente una riga nel formato
+/// ```format
+/// {"comando":"?","argomenti":["1","2"],"risultato":{"colore":"r","intensita":3}}
+/// ```
+/// dove `comando` è la forma breve del comando e `risultato` è `null` se il comando
+/// non ha risultato
+///
+/// # Arguments
+/// * `piano` - piano su cui eseguire il comando
+/// * `riga` - riga di input contenente il comando e i suoi argomenti
+/// * `formato` - formato dell'output
+/// * `logger` - funzione che riceve ogni riga di output
+///
+/// # Returns
+/// * `Ok(Esito)` - se il comando è stato eseguito
+/// * `Err(String)` - la descrizione dell'errore se il comando è malformato,
+///   in tal caso il piano **non** è modificato
+fn esegui(
+    piano: &mut Piano,
+    riga: &str,
+    formato: Formato,
+    logger: &mut dyn FnMut(String),
+) -> Result<Esito, String> {
+    let parti = tokenizza(riga);
+    if parti.is_empty() {
+        return Ok(Esito::Continua);
+    }
+
+    // forma breve del comando, i comandi non in tabella restano invariati
+    let lettera = cerca_comando(parti[0]).map_or(parti[0], |comando| comando.lettera);
+
+    // risponde con `testo` in formato `Testo` se presente, con `json` (o `testo` come
+    // stringa JSON se assente) in formato `Json`
+    let mut rispondi = |testo: Option<String>, json: Option<String>| match formato {
+        Formato::Testo => {
+            if let Some(testo) = testo {
+                logger(testo)
+            }
+        }
+        Formato::Json => logger(format!(
+            "{{\"comando\":{},\"argomenti\":{},\"risultato\":{}}}",
+            lettera.json(),
+            parti[1..].json(),
+            json.unwrap_or_else(|| testo.json())
+        )),
+    };
+
+    match lettera {
+        "C" => {
+            controlla(parti.len() == 5)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            let colore: String = String::from(parti[3]);
+            let i: u32 = intensita(parti[4])?;
+            piano.colora(x, y, colore, i);
+        }
+        "F" | "l" => {
+            controlla(parti.len() == 7)?;
+            let x1: i32 = argomento(parti[1])?;
+            let y1: i32 = argomento(parti[2])?;
+            let x2: i32 = argomento(parti[3])?;
+            let y2: i32 = argomento(parti[4])?;
+            let colore: String = String::from(parti[5]);
+            let i: u32 = intensita(parti[6])?;
+            match lettera {
+                "F" => piano.colora_rettangolo(x1, y1, x2, y2, colore, i),
+                _ => piano.colora_linea(x1, y1, x2, y2, colore, i),
+            }
+        }
+        "c" => {
+            controlla(parti.len() == 6)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            let raggio: u32 = argomento(parti[3])?;
+            controlla(raggio <= i32::MAX as u32)?;
+            let colore: String = String::from(parti[4]);
+            let i: u32 = intensita(parti[5])?;
+            piano.colora_cerchio(x, y, raggio, colore, i);
+        }
+        "f" => {
+            controlla(parti.len() == 5)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            let colore: String = String::from(parti[3]);
+            let i: u32 = intensita(parti[4])?;
+            piano.riempi(x, y, colore, i);
+        }
+        "X" => {
+            controlla(parti.len() == 5)?;
+            let x1: i32 = argomento(parti[1])?;
+            let y1: i32 = argomento(parti[2])?;
+            let x2: i32 = argomento(parti[3])?;
+            let y2: i32 = argomento(parti[4])?;
+            piano.spegni_rettangolo(x1, y1, x2, y2);
+        }
+        "I" => {
+            controlla(parti.len() == 4 || parti.len() == 5)?;
+            let x: i32 = argomento(parti[2])?;
+            let y: i32 = argomento(parti[3])?;
+            let contenuto = std::fs::read(parti[1])
+                .map_err(|_| format!("errore aprendo il file {}", parti[1]))?;
+            if contenuto.starts_with(b"P3") || contenuto.starts_with(b"P6") {
+                let i: u32 = match parti.get(4) {
+                    Some(i) => intensita(i)?,
+                    None => 1,
+                };
+                piano.importa_ppm(&contenuto, x, y, i)?;
+            } else {
+                controlla(parti.len() == 4)?;
+                let griglia = String::from_utf8(contenuto)
+                    .map_err(|_| format!("errore leggendo il file {}", parti[1]))?;
+                piano.importa_griglia(&griglia, x, y)?;
+            }
+        }
+        "K" => {
+            controlla(parti.len() == 3)?;
+            let contenuto = std::fs::read_to_string(parti[2])
+                .map_err(|_| format!("errore aprendo il file {}", parti[2]))?;
+            match parti[1] {
+                "piastrelle" => piano.importa_csv(&contenuto)?,
+                "regole" => piano.importa_regole(&contenuto)?,
+                _ => return Err(String::from("input non valido")),
+            }
+        }
+        "E" => {
+            controlla(parti.len() == 2 || parti.len() == 3)?;
+            let (testo, json) = match parti[1] {
+                "piastrelle" => (piano.esporta_csv(), None),
+                "regole" => {
+                    let regole = piano.esporta_regole();
+                    (regole.clone(), Some(regole))
+                }
+                _ => return Err(String::from("input non valido")),
+            };
+            match parti.get(2) {
+                Some(file) => std::fs::write(file, format!("{}\n", testo.trim_end()))
+                    .map_err(|_| format!("errore scrivendo il file {file}"))?,
+                None => rispondi(Some(String::from(testo.trim_end())), json),
+            }
+        }
+        "S" => {
+            controlla(parti.len() == 3)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            piano.spegni(x, y);
+        }
+        "r" => {
+            controlla(parti.len() > 1)?;
+            let regola = parti[1..].join(" ");
+            piano.valida_regola(&regola)?;
+            piano.regola(regola);
+        }
+        "?" => {
+            controlla(parti.len() == 3)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            let stato = piano.stato(x, y);
+            let testo = stato
+                .as_ref()
+                .map(|Colorazione { colore, intensita }| format!("{} {}", colore, intensita));
+            rispondi(testo, Some(stato.json()));
+        }
+        "s" => {
+            controlla(parti.len() == 1)?;
+            rispondi(Some(piano.stampa()), Some(piano.esporta_regole()));
+        }
+        "u" => {
+            controlla(parti.len() == 1)?;
+            rispondi(Some(piano.stampa_utilizzo()), None);
+        }
+        "R" => {
+            controlla(parti.len() > 1)?;
+            if parti.len() == 2 {
+                let i: usize = argomento(parti[1])?;
+                piano.rimuovi_regola(i);
+            } else {
+                let regola = parti[1..].join(" ");
+                piano.valida_regola(&regola)?;
+                piano.rimuovi_regola_testo(regola);
+            }
+        }
+        "M" => {
+            controlla(parti.len() > 2)?;
+            let i: usize = argomento(parti[1])?;
+            let regola = parti[2..].join(" ");
+            piano.valida_regola(&regola)?;
+            piano.sostituisci_regola(i, regola);
+        }
+        "a" => {
+            controlla(parti.len() == 1)?;
+            rispondi(Some(piano.stampa_analisi()), None);
+        }
+        "Z" => {
+            controlla(parti.len() == 1)?;
+            piano.azzera_utilizzo();
+        }
+        "b" => {
+            controlla(parti.len() == 3)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            let totale = piano.blocco(x, y);
+            rispondi(Some(totale.to_string()), Some(totale.json()));
+        }
+        "B" => {
+            controlla(parti.len() == 3)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            let totale = piano.blocco_omogeneo(x, y);
+            rispondi(Some(totale.to_string()), Some(totale.json()));
+        }
+        "n" | "w" | "g" => {
+            controlla(parti.len() == 5)?;
+            let x1: i32 = argomento(parti[1])?;
+            let y1: i32 = argomento(parti[2])?;
+            let x2: i32 = argomento(parti[3])?;
+            let y2: i32 = argomento(parti[4])?;
+            match lettera {
+                "n" => {
+                    let n = piano.conta_rettangolo(x1, y1, x2, y2);
+                    rispondi(Some(n.to_string()), Some(n.json()))
+                }
+                "w" => {
+                    let totale = piano.intensita_rettangolo(x1, y1, x2, y2);
+                    rispondi(Some(totale.to_string()), Some(totale.json()))
+                }
+                _ => {
+                    let colori = piano.istogramma_rettangolo(x1, y1, x2, y2);
+                    let testo: Vec<String> = colori
+                        .iter()
+                        .map(|(colore, n)| format!("{colore} {n}"))
+                        .collect();
+                    let json: Vec<String> = colori
+                        .iter()
+                        .map(|(colore, n)| format!("{}:{n}", colore.json()))
+                        .collect();
+                    // un rettangolo senza piastrelle accese ha comunque una risposta
+                    let testo = if colori.is_empty() {
+                        String::from("nessuno")
+                    } else {
+                        testo.join(" ")
+                    };
+                    rispondi(Some(testo), Some(format!("{{{}}}", json.join(","))))
+                }
+            }
+        }
+        "N" => {
+            let omogeneo = match parti[1..] {
+                [] => false,
+                ["omogenei"] => true,
+                _ => return Err(String::from("input non valido")),
+            };
+            rispondi(Some(piano.stampa_blocchi(omogeneo)), None);
+        }
+        "e" => {
+            controlla(parti.len() == 3)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            rispondi(Some(piano.spiega(x, y)), None);
+        }
+        "v" => {
+            controlla(parti.len() == 3)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            rispondi(Some(piano.stampa_anteprima_blocco(x, y)), None);
+        }
+        "p" => {
+            controlla(parti.len() == 3)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            piano.propaga(x, y);
+        }
+        "P" => {
+            controlla(parti.len() == 3)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            piano.propaga_blocco(x, y);
+        }
+        "A" => {
+            controlla(parti.len() == 4)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            let ordine = match parti[3] {
+                "bfs" => Ordine::Bfs,
+                "righe" => Ordine::Righe,
+                "coordinate" => Ordine::Coordinate,
+                _ => return Err(String::from("input non valido")),
+            };
+            piano.propaga_blocco_sequenziale(x, y, ordine);
+        }
+        "o" => {
+            controlla(parti.len() == 1)?;
+            piano.ordina();
+        }
+        "T" => {
+            controlla(parti.len() == 2)?;
+            let thread: usize = argomento(parti[1])?;
+            controlla(thread > 0)?;
+            piano.imposta_thread(thread);
+        }
+        "t" => {
+            controlla(parti.len() > 3)?;
+            let x: i32 = argomento(parti[1])?;
+            let y: i32 = argomento(parti[2])?;
+            let intensita = piano.pista(x, y, parti[3..].join(" "));
+            rispondi(intensita.map(|i| i.to_string()), Some(intensita.json()));
+        }
+        "L" => {
+            controlla(parti.len() == 5)?;
+            let x1: i32 = argomento(parti[1])?;
+            let y1: i32 = argomento(parti[2])?;
+            let x2: i32 = argomento(parti[3])?;
+            let y2: i32 = argomento(parti[4])?;
+            let dist = piano.lung(x1, y1, x2, y2);
+            rispondi(dist.map(|d| d.to_string()), Some(dist.json()));
+        }
+        "i" => rispondi(Some(String::from("TODO intensità")), None),
+        "m" => rispondi(Some(String::from("TODO perimetro")), None),
+        "h" => rispondi(Some(aiuto()), None),
+        "q" => return Ok(Esito::Termina),
+        comando => rispondi(
+            Some(format!(
+                "comando sconosciuto {comando}, scrivi help per l'elenco dei comandi"
+            )),
+            None,
+        ),
+    }
+
+    Ok(Esito::Continua)
+}
+
+/// Restituisce le piastrelle modificate da un comando, una riga per piastrella nel formato
+/// `~ x y prima -> dopo`, dove `prima` e `dopo` sono `colore intensita` o `spenta`
+///
+/// # Arguments
+/// * `modifiche` - piastrelle modificate dal comando (vedi `Piano::modifiche`)
+///
+/// # Returns
+/// * `Vec<String>` che rappresenta le modifiche nel formato descritto, nello stesso ordine
+fn mutazioni(modifiche: &[Modifica]) -> Vec<String> {
+    let stato = |colorazione: &Option<Colorazione>| match colorazione {
+        Some(Colorazione { colore, intensita }) => format!("{colore} {intensita}"),
+        None => String::from("spenta"),
+    };
+    modifiche
+        .iter()
+        .map(
+            |Modifica {
+                 piastrella,
+                 prima,
+                 dopo,
+             }| {
+                format!(
+                    "~ {} {} {} -> {}",
+                    piastrella.x,
+                    piastrella.y,
+                    stato(prima),
+                    stato(dopo)
+                )
+            },
+        )
+        .collect()
+}
+
+/// Capacità del buffer di output di `run`: l'output è scritto a blocchi, non una
+/// chiamata di sistema per riga
+const CAPACITA_OUTPUT: usize = 1 << 16;
+
+/// Esegue i comandi letti da `input` (o dallo standard input), scrivendo l'output in
+/// `output` (o sullo standard output). Con una `traccia` diversa da `Nessuna` ogni riga
+/// di output è preceduta da `numero comando: `, dove `numero` è la riga di input (da 1)
+/// e `comando` la riga stessa senza commenti
+///
+/// # Panics
+/// * se un file non può essere aperto o scritto, se un comando è malformato o se
+///   un'asserzione `expect` fallisce, indicando la riga di input
+pub fn run(input: Option<String>, output: Option<String>, traccia: Traccia) {
+    match output {
+        Some(filename) => match File::create(filename) {
+            Ok(file) => run_su(input, file, traccia),
+            Err(..) => panic!("errore aprendo il file"),
+        },
+        None => run_su(input, io::stdout().lock(), traccia),
+    }
+}
+
+/// Come `run`, ma scrive l'output in `output`, per esempio un `Vec<u8>` per usare il
+/// piano da un altro programma. L'output passa da un buffer, svuotato quando l'esecuzione
+/// termina: con `q`, alla fine dell'input o ad un errore (prima del panic, quindi
+/// l'output dei comandi precedenti è sempre scritto)
+///
+/// # Panics
+/// * se `input` non può essere aperto, se `output` non può essere scritto, se un comando
+///   è malformato o se un'asserzione `expect` fallisce, indicando la riga di input
+pub fn run_su(input: Option<String>, output: impl Write, traccia: Traccia) {
+    let mut output = BufWriter::with_capacity(CAPACITA_OUTPUT, output);
+    let mut logger = |s: String| {
+        if writeln!(output, "{s}").is_err() {
+            panic!("errore scrivendo l'output")
+        }
+    };
+    let esito = _esegui_input(input, traccia, &mut logger);
+
+    if output.flush().is_err() {
+        panic!("errore scrivendo l'output")
+    }
+    if let Err(errore) = esito {
+        panic!("{errore}");
+    }
+}
+
+/// Esegue i comandi letti da `input` (vedi `run`) su un nuovo piano, fino a `q` o alla
+/// fine dell'input, passando l'output a `logger`
+///
+/// # Returns
+/// * `Ok(())` - se tutti i comandi sono stati eseguiti
+/// * `Err(String)` - la descrizione dell'errore, con la riga di input se dovuto ad un comando
+fn _esegui_input(
+    input: Option<String>,
+    traccia: Traccia,
+    logger: &mut dyn FnMut(String),
+) -> Result<(), String> {
+    let mut piano = Piano::new();
+    if traccia == Traccia::Mutazioni {
+        piano.registra_modifiche();
+    }
+
+    // le inclusioni sono relative al file di input, o alla cartella corrente
+    let mut interprete = match &input {
+        Some(filename) => Interprete::per_file(Path::new(filename)),
+        None => Interprete::new(Path::new(".")),
+    };
+
+    let input: Box<dyn BufRead> = match input {
+        Some(filename) => match File::open(filename) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(..) => panic!("errore aprendo il file"),
+        },
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+
+    for (numero, line) in input.lines().enumerate() {
+        let line = line.unwrap();
+
+        if traccia == Traccia::Nessuna {
+            match interprete.esegui(&mut piano, &line, logger) {
+                Ok(Esito::Continua) => continue,
+                Ok(Esito::Termina) => return Ok(()),
+                Err(errore) => return Err(format!("riga {}: {errore}", numero + 1)),
+            }
+        }
+
+        let mut righe = Vec::new();
+        let esito = interprete.esegui(&mut piano, &line, &mut |s| righe.push(s));
+        if traccia == Traccia::Mutazioni {
+            righe.extend(mutazioni(&piano.modifiche()));
+        }
+
+        let comando = tokenizza(&line).join(" ");
+        for riga in righe.iter().flat_map(|s| s.lines()) {
+            logger(format!("{} {comando}: {riga}", numero + 1));
+        }
+        match esito {
+            Ok(Esito::Continua) => (),
+            Ok(Esito::Termina) => return Ok(()),
+            Err(errore) => return Err(format!("riga {}: {errore}", numero + 1)),
+        }
+    }
+
+    interprete.termina()
+}
+
+/// Restituisce il messaggio di un panic catturato con `catch_unwind`, vuoto se il panic
+/// non ha un messaggio testuale
+fn messaggio_panico(errore: &(dyn Any + Send)) -> String {
+    errore
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| errore.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+mod archivio;
+mod formati;
+pub mod generatore;
+mod indice;
+pub mod minimizzatore;
+mod parallelo;
+pub mod repl;
+mod script;
+mod spaziale;
+mod tavolozza;
+
+#[cfg(test)]
+mod differential_tests;
+#[cfg(test)]
+mod io_tests;
+#[cfg(test)]
+mod unit_tests;
@@ -0,0 +1,244 @@
+use std::fs;
+use std::io::Write;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::script::VERIFICABILI;
+use crate::{cerca_comando, messaggio_panico, run, tokenizza, Traccia};
+
+/// Riduce `elementi` ad una sottosequenza minimale per cui `fallisce` è ancora vero, con
+/// l'algoritmo delta debugging (ddmin): prova a tenere solo una delle `n` parti o a
+/// toglierne una, raddoppiando `n` quando nessuna prova fallisce. Il risultato è
+/// 1-minimale: togliendo un qualsiasi elemento `fallisce` diventa falso
+///
+/// # Arguments
+/// * `elementi` - sequenza per cui `fallisce` è vero
+/// * `fallisce` - predicato di fallimento, chiamato su sottosequenze di `elementi`
+///
+/// # Returns
+/// * la sottosequenza minimale, nell'ordine originale
+pub fn minimizza<T: Clone>(elementi: Vec<T>, mut fallisce: impl FnMut(&[T]) -> bool) -> Vec<T> {
+    let mut elementi = elementi;
+    let mut n = 2;
+
+    while elementi.len() >= 2 {
+        let dimensione = elementi.len().div_ceil(n);
+        let parti: Vec<Vec<T>> = elementi.chunks(dimensione).map(<[T]>::to_vec).collect();
+
+        if let Some(parte) = parti.iter().find(|parte| fallisce(parte)) {
+            elementi = parte.clone();
+            n = 2;
+            continue;
+        }
+
+        let complementi = (0..parti.len()).map(|i| {
+            parti
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .flat_map(|(_, parte)| parte.iter().cloned())
+                .collect::<Vec<T>>()
+        });
+        if let Some(complemento) = complementi
+            .filter(|_| parti.len() > 2)
+            .find(|complemento| fallisce(complemento))
+        {
+            elementi = complemento;
+            n = (n - 1).max(2);
+            continue;
+        }
+
+        if n >= elementi.len() {
+            break;
+        }
+        n = (2 * n).min(elementi.len());
+    }
+
+    elementi
+}
+
+/// Criterio di fallimento di una sequenza di comandi, le righe sono indicate con la loro
+/// posizione (da 0) nell'input completo
+pub enum Criterio {
+    /// `run` va in panic
+    Panico,
+    /// il programma, eseguito con i comandi come standard input, termina in modo anomalo:
+    /// per un segnale o con un codice diverso da 0 e da 101 (panic)
+    Crash(PathBuf),
+    /// la riga `riga` dell'input produce ancora `output`, l'output sbagliato dell'input
+    /// completo (l'output atteso vale solo per l'input completo)
+    Risposta { riga: usize, output: Vec<String> },
+}
+
+impl Criterio {
+    /// Costruisce il criterio `Risposta` per il comando della prima riga in cui l'output
+    /// dei comandi differisce da `atteso`. Se la riga ottenuta è quella attesa subito
+    /// dopo, o l'output è finito, manca una riga: il criterio è per il comando senza
+    /// output che doveva produrla (vedi `silenzioso`), che deve restare senza output
+    ///
+    /// # Arguments
+    /// * `righe` - comandi dell'input completo
+    /// * `atteso` - output atteso dell'input completo
+    /// * `cartella` - cartella dei file temporanei, e delle eventuali inclusioni
+    ///
+    /// # Returns
+    /// * `Err(String)` - se l'output è uguale a quello atteso
+    pub fn risposta(righe: &[String], atteso: &str, cartella: &Path) -> Result<Criterio, String> {
+        let righe: Vec<(usize, String)> = righe.iter().cloned().enumerate().collect();
+        let output = esegui(&righe, Traccia::Righe, cartella)
+            .map_err(|errore| format!("l'input completo va in panic ({errore})"))?;
+
+        let atteso: Vec<&str> = atteso.lines().collect();
+        let diversa = (0..output.len().max(atteso.len()))
+            .find(|i| output.get(*i).map(|(_, riga)| riga.as_str()) != atteso.get(*i).copied())
+            .ok_or_else(|| String::from("nessuna riga di output sbagliata"))?;
+
+        let riga = match output.get(diversa) {
+            Some((riga, ottenuta)) if atteso.get(diversa + 1) != Some(&ottenuta.as_str()) => *riga,
+            successiva => {
+                let precedente = diversa.checked_sub(1).map(|i| output[i].0);
+                let successiva = successiva.map_or(righe.len(), |(riga, _)| *riga);
+                silenzioso(&righe, precedente, successiva)
+            }
+        };
+
+        Ok(Criterio::Risposta {
+            riga,
+            output: risposte(&output, riga),
+        })
+    }
+
+    /// Controlla se i comandi falliscono secondo il criterio
+    ///
+    /// # Arguments
+    /// * `righe` - comandi, con la loro posizione nell'input completo
+    /// * `cartella` - cartella dei file temporanei, e delle eventuali inclusioni
+    pub fn fallisce(&self, righe: &[(usize, String)], cartella: &Path) -> bool {
+        match self {
+            Criterio::Panico => esegui(righe, Traccia::Nessuna, cartella).is_err(),
+            Criterio::Crash(programma) => {
+                let Ok(mut processo) = Command::new(programma)
+                    .current_dir(cartella)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()
+                else {
+                    return false;
+                };
+                if let Some(mut input) = processo.stdin.take() {
+                    // il processo può terminare prima di leggere tutto l'input
+                    let _ = input.write_all(testo(righe).as_bytes());
+                }
+                processo
+                    .wait()
+                    .is_ok_and(|esito| !matches!(esito.code(), Some(0 | 101)))
+            }
+            Criterio::Risposta { riga, output } => {
+                righe.iter().any(|(indice, _)| indice == riga)
+                    && esegui(righe, Traccia::Righe, cartella)
+                        .is_ok_and(|ottenuto| risposte(&ottenuto, *riga) == *output)
+            }
+        }
+    }
+}
+
+/// Sceglie il comando che doveva produrre una riga mancante, tra quelli senza output
+/// successivi alla riga `precedente` e precedenti alla riga `successiva`: il primo
+/// comando verificabile con `expect` (l'unico genere di comando che può non rispondere),
+/// altrimenti il primo comando. Se non ce ne sono, la riga mancava all'output di
+/// `precedente`
+///
+/// # Arguments
+/// * `righe` - comandi dell'input completo, con la loro posizione
+/// * `precedente` - riga del comando dell'ultima riga di output corretta, se c'è
+/// * `successiva` - riga del comando della riga di output sbagliata, o il numero di
+///   righe se l'output è finito
+fn silenzioso(righe: &[(usize, String)], precedente: Option<usize>, successiva: usize) -> usize {
+    let candidati = precedente.map_or(0, |riga| riga + 1)..successiva;
+    let verificabile = candidati.clone().find(|riga| {
+        tokenizza(&righe[*riga].1)
+            .first()
+            .and_then(|nome| cerca_comando(nome))
+            .is_some_and(|comando| VERIFICABILI.contains(&comando.lettera))
+    });
+    verificabile
+        .or(candidati.clone().next())
+        .or(precedente)
+        .unwrap_or(successiva)
+}
+
+/// Le righe separate da `\n`, ognuna terminata da `\n`
+fn testo(righe: &[(usize, String)]) -> String {
+    righe.iter().map(|(_, riga)| format!("{riga}\n")).collect()
+}
+
+/// Contatore dei file temporanei, distinti anche tra thread diversi
+static ESECUZIONI: AtomicUsize = AtomicUsize::new(0);
+
+/// Esegue i comandi con `run` in un file temporaneo di `cartella`
+///
+/// # Returns
+/// * `Ok(Vec)` - le righe di output, con la posizione nell'input completo del comando che
+///   le ha prodotte (se `traccia` non è `Nessuna`, altrimenti 0) e il loro contenuto
+/// * `Err(String)` - il messaggio del panic
+fn esegui(
+    righe: &[(usize, String)],
+    traccia: Traccia,
+    cartella: &Path,
+) -> Result<Vec<(usize, String)>, String> {
+    let nome = format!(
+        ".minimizza_{}_{}",
+        std::process::id(),
+        ESECUZIONI.fetch_add(1, Ordering::Relaxed)
+    );
+    let input = cartella.join(format!("{nome}.in"));
+    let output = cartella.join(format!("{nome}.out"));
+    fs::write(&input, testo(righe)).map_err(|errore| errore.to_string())?;
+
+    let (percorso_input, percorso_output) =
+        (input.display().to_string(), output.display().to_string());
+    let esito = panic::catch_unwind(|| run(Some(percorso_input), Some(percorso_output), traccia));
+    let contenuto = fs::read_to_string(&output).unwrap_or_default();
+    let _ = fs::remove_file(&input);
+    let _ = fs::remove_file(&output);
+
+    if let Err(errore) = esito {
+        return Err(messaggio_panico(&*errore));
+    }
+    if traccia == Traccia::Nessuna {
+        return Ok(contenuto
+            .lines()
+            .map(|riga| (0, String::from(riga)))
+            .collect());
+    }
+
+    // toglie il prefisso `numero comando: ` della traccia
+    let mut risultato = Vec::new();
+    for riga in contenuto.lines() {
+        let numero: usize = riga
+            .split(' ')
+            .next()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0);
+        let Some((indice, comando)) = numero.checked_sub(1).and_then(|n| righe.get(n)) else {
+            continue;
+        };
+        let prefisso = format!("{numero} {}: ", tokenizza(comando).join(" "));
+        if let Some(output) = riga.strip_prefix(&prefisso) {
+            risultato.push((*indice, String::from(output)));
+        }
+    }
+    Ok(risultato)
+}
+
+/// Le righe di output prodotte dalla riga di input `riga`
+fn risposte(output: &[(usize, String)], riga: usize) -> Vec<String> {
+    output
+        .iter()
+        .filter(|(indice, _)| *indice == riga)
+        .map(|(_, output)| output.clone())
+        .collect()
+}
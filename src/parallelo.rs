@@ -0,0 +1,75 @@
+use std::num::NonZeroUsize;
+use std::thread;
+
+/// Minimo di elementi per thread: con meno elementi avviare un thread costa più
+/// del lavoro che gli si affida
+const MINIMO_PER_THREAD: usize = 512;
+
+/// Restituisce il numero di thread disponibili sulla macchina (almeno 1)
+pub fn disponibili() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Divide `elementi` in porzioni contigue, una per thread, e applica `lavoro` ad ogni
+/// porzione su al più `thread` thread. Il risultato **non** dipende dal numero di thread
+/// se non ne dipende la concatenazione dei risultati delle porzioni
+///
+/// # Arguments
+/// * `elementi` - elementi da dividere
+/// * `thread` - numero massimo di thread da usare
+/// * `lavoro` - funzione che riceve la posizione in `elementi` del primo elemento
+///   della porzione e la porzione
+///
+/// # Returns
+/// * i risultati di `lavoro`, nell'ordine delle porzioni
+pub fn per_porzioni<T: Sync, R: Send>(
+    elementi: &[T],
+    thread: usize,
+    lavoro: impl Fn(usize, &[T]) -> R + Sync,
+) -> Vec<R> {
+    let porzioni = thread
+        .min(elementi.len().div_ceil(MINIMO_PER_THREAD))
+        .max(1);
+    if porzioni == 1 {
+        // evita di avviare un thread per una sola porzione
+        return vec![lavoro(0, elementi)];
+    }
+
+    let lunghezza = elementi.len().div_ceil(porzioni);
+    let lavoro = &lavoro;
+    thread::scope(|ambito| {
+        let gestori: Vec<_> = elementi
+            .chunks(lunghezza)
+            .enumerate()
+            .map(|(i, porzione)| ambito.spawn(move || lavoro(i * lunghezza, porzione)))
+            .collect();
+        gestori
+            .into_iter()
+            // un panic in un thread si propaga al chiamante, con il suo messaggio
+            .map(|gestore| {
+                gestore
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    })
+}
+
+/// Restituisce il rappresentante dell'insieme di `elemento` in una union-find
+/// rappresentata dal vettore dei genitori, comprimendo il cammino
+pub fn trova(genitori: &mut [usize], mut elemento: usize) -> usize {
+    let mut radice = elemento;
+    while genitori[radice] != radice {
+        radice = genitori[radice];
+    }
+    while genitori[elemento] != radice {
+        elemento = std::mem::replace(&mut genitori[elemento], radice);
+    }
+    radice
+}
+
+/// Unisce gli insiemi di `a` e `b`: il rappresentante è il minore dei due
+pub fn unisci(genitori: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (trova(genitori, a), trova(genitori, b));
+    genitori[a.max(b)] = a.min(b);
+}
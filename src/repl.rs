@@ -0,0 +1,355 @@
+use std::io::{self, BufRead, Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::process::{Command, Stdio};
+
+use std::path::Path;
+
+use crate::script::{Interprete, DIRETTIVE};
+use crate::{messaggio_panico, Esito, Piano, COMANDI};
+
+const PROMPT: &str = "piastrelle> ";
+
+/// Esegue i comandi letti da terminale, riga per riga, finché non viene letto `q`
+/// o la fine dell'input. A differenza di `run` un comando malformato **non** termina
+/// l'esecuzione: l'errore viene stampato e il piano resta invariato. Anche un panic
+/// durante un comando viene stampato come errore, ma il piano può restare modificato
+/// in parte
+pub fn interattivo() {
+    let mut piano = Piano::new();
+    let mut editor = Editor::new();
+    let mut interprete = Interprete::new(Path::new("."));
+
+    let comandi: Vec<String> = COMANDI
+        .iter()
+        .flat_map(|comando| [comando.lettera, comando.nome])
+        .chain(DIRETTIVE)
+        .map(String::from)
+        .collect();
+
+    println!("scrivi help per l'elenco dei comandi");
+
+    // il messaggio dei panic è stampato come errore, non dal gestore di default
+    let gestore = panic::take_hook();
+    panic::set_hook(Box::new(|_| ()));
+
+    while let Some(riga) = editor.leggi(&comandi, &piano.colori()) {
+        let esito = panic::catch_unwind(AssertUnwindSafe(|| {
+            interprete.esegui(&mut piano, &riga, &mut |s| println!("{s}"))
+        }));
+        match esito {
+            Ok(Ok(Esito::Continua)) => (),
+            Ok(Ok(Esito::Termina)) => break,
+            Ok(Err(errore)) => eprintln!("errore: {errore}"),
+            Err(panico) => eprintln!("errore: {}", messaggio_panico(&*panico)),
+        }
+    }
+
+    panic::set_hook(gestore);
+}
+
+/// Completa l'ultima parola di `riga` (il comando se è la prima, altrimenti un colore)
+///
+/// # Arguments
+/// * `riga` - riga da completare
+/// * `comandi` - candidati per la prima parola
+/// * `colori` - candidati per le parole successive
+///
+/// # Returns
+/// * la riga completata: con il candidato seguito da uno spazio se ne esiste uno solo,
+///   altrimenti con il prefisso comune a tutti i candidati
+/// * i candidati possibili se sono più di uno, vuoto altrimenti
+pub(crate) fn completa(riga: &str, comandi: &[String], colori: &[String]) -> (String, Vec<String>) {
+    let (inizio, parola) = match riga.rfind(' ') {
+        Some(i) => riga.split_at(i + 1),
+        None => ("", riga),
+    };
+    let candidati = if inizio.trim().is_empty() {
+        comandi
+    } else {
+        colori
+    };
+
+    let candidati: Vec<&String> = candidati
+        .iter()
+        .filter(|candidato| candidato.starts_with(parola))
+        .collect();
+
+    match candidati.as_slice() {
+        [] => (String::from(riga), Vec::new()),
+        [candidato] => (format!("{inizio}{candidato} "), Vec::new()),
+        [primo, altri @ ..] => {
+            let comune = altri.iter().fold(primo.as_str(), |comune, candidato| {
+                let lunghezza = comune
+                    .char_indices()
+                    .zip(candidato.chars())
+                    .take_while(|((.., a), b)| a == b)
+                    .last()
+                    .map(|((i, a), ..)| i + a.len_utf8())
+                    .unwrap_or(0);
+                &comune[..lunghezza]
+            });
+            (
+                format!("{inizio}{comune}"),
+                candidati.into_iter().cloned().collect(),
+            )
+        }
+    }
+}
+
+/// Tasto letto dal terminale
+#[derive(PartialEq, Debug)]
+pub(crate) enum Tasto {
+    Carattere(char),
+    Invio,
+    Cancella,
+    CancellaAvanti,
+    Tab,
+    Su,
+    Giu,
+    Sinistra,
+    Destra,
+    Inizio,
+    Fine,
+    Interrompi,
+    FineInput,
+    Altro,
+}
+
+/// Legge un tasto (eventualmente una sequenza di escape o un carattere UTF-8) da `input`
+pub(crate) fn leggi_tasto(input: &mut impl Read) -> io::Result<Tasto> {
+    let mut leggi_byte = || -> io::Result<Option<u8>> {
+        let mut byte = [0u8];
+        match input.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    };
+
+    let byte = match leggi_byte()? {
+        Some(byte) => byte,
+        None => return Ok(Tasto::FineInput),
+    };
+
+    let tasto = match byte {
+        b'\r' | b'\n' => Tasto::Invio,
+        127 | 8 => Tasto::Cancella,
+        b'\t' => Tasto::Tab,
+        1 => Tasto::Inizio,
+        5 => Tasto::Fine,
+        3 => Tasto::Interrompi,
+        4 => Tasto::FineInput,
+        27 => match leggi_byte()? {
+            Some(b'[') | Some(b'O') => match leggi_byte()? {
+                Some(b'A') => Tasto::Su,
+                Some(b'B') => Tasto::Giu,
+                Some(b'C') => Tasto::Destra,
+                Some(b'D') => Tasto::Sinistra,
+                Some(b'H') => Tasto::Inizio,
+                Some(b'F') => Tasto::Fine,
+                Some(b'3') => {
+                    leggi_byte()?;
+                    Tasto::CancellaAvanti
+                }
+                Some(b'0'..=b'9') => {
+                    // sequenza non gestita (es. `ESC [ 1 5 ~`), la consuma
+                    while let Some(b'0'..=b'9' | b';') = leggi_byte()? {}
+                    Tasto::Altro
+                }
+                _ => Tasto::Altro,
+            },
+            _ => Tasto::Altro,
+        },
+        0..=31 => Tasto::Altro,
+        _ => {
+            let lunghezza = match byte {
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF7 => 4,
+                _ => 1,
+            };
+            let mut bytes = vec![byte];
+            for _ in 1..lunghezza {
+                if let Some(byte) = leggi_byte()? {
+                    bytes.push(byte);
+                }
+            }
+            match String::from_utf8(bytes).ok().and_then(|s| s.chars().next()) {
+                Some(carattere) => Tasto::Carattere(carattere),
+                None => Tasto::Altro,
+            }
+        }
+    };
+    Ok(tasto)
+}
+
+/// Terminale in modalità non canonica, senza eco e senza segnali: Ctrl-C arriva come
+/// byte (`Tasto::Interrompi`) invece di terminare il processo, le impostazioni precedenti
+/// vengono ripristinate al drop
+struct TerminaleRaw {
+    impostazioni: String,
+}
+
+impl TerminaleRaw {
+    /// Imposta il terminale in modalità non canonica tramite `stty`
+    ///
+    /// # Returns
+    /// * `None` - se non è possibile modificare le impostazioni del terminale
+    fn attiva() -> Option<Self> {
+        let impostazioni = Command::new("stty")
+            .arg("-g")
+            .stdin(Stdio::inherit())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let impostazioni = String::from_utf8(impostazioni.stdout).ok()?;
+
+        Command::new("stty")
+            .args(["-icanon", "-echo", "-isig", "min", "1", "time", "0"])
+            .stdin(Stdio::inherit())
+            .status()
+            .ok()
+            .filter(|status| status.success())?;
+
+        Some(Self {
+            impostazioni: String::from(impostazioni.trim()),
+        })
+    }
+}
+
+impl Drop for TerminaleRaw {
+    fn drop(&mut self) {
+        let _ = Command::new("stty")
+            .arg(&self.impostazioni)
+            .stdin(Stdio::inherit())
+            .status();
+    }
+}
+
+/// Editor di una riga di input: modifica della riga, storia dei comandi (frecce su e giù)
+/// e completamento (tab)
+struct Editor {
+    storia: Vec<String>,
+}
+
+impl Editor {
+    fn new() -> Self {
+        Self { storia: Vec::new() }
+    }
+
+    /// Legge una riga dal terminale, mostrando il prompt
+    ///
+    /// # Arguments
+    /// * `comandi` - candidati per il completamento della prima parola
+    /// * `colori` - candidati per il completamento delle parole successive
+    ///
+    /// # Returns
+    /// * `Some(String)` - la riga letta
+    /// * `None` - alla fine dell'input
+    fn leggi(&mut self, comandi: &[String], colori: &[String]) -> Option<String> {
+        let riga = match TerminaleRaw::attiva() {
+            Some(_terminale) => self._leggi_raw(comandi, colori).ok()?,
+            None => Self::_leggi_semplice().ok()?,
+        }?;
+
+        if !riga.trim().is_empty() && self.storia.last() != Some(&riga) {
+            self.storia.push(riga.clone());
+        }
+        Some(riga)
+    }
+
+    /// Legge una riga senza modifica, storia e completamento, se il terminale non
+    /// supporta la modalità non canonica
+    fn _leggi_semplice() -> io::Result<Option<String>> {
+        print!("{PROMPT}");
+        io::stdout().flush()?;
+
+        let mut riga = String::new();
+        match io::stdin().lock().read_line(&mut riga)? {
+            0 => Ok(None),
+            _ => Ok(Some(String::from(riga.trim_end_matches(['\r', '\n'])))),
+        }
+    }
+
+    /// Legge una riga con il terminale in modalità non canonica
+    fn _leggi_raw(&self, comandi: &[String], colori: &[String]) -> io::Result<Option<String>> {
+        let mut stdin = io::stdin().lock();
+        let mut stdout = io::stdout().lock();
+
+        let mut riga: Vec<char> = Vec::new();
+        let mut cursore = 0;
+        // posizione nella storia e riga in scrittura prima di scorrerla
+        let mut indice = self.storia.len();
+        let mut bozza: Vec<char> = Vec::new();
+
+        let ridisegna = |stdout: &mut io::StdoutLock, riga: &[char], cursore: usize| {
+            let testo: String = riga.iter().collect();
+            write!(stdout, "\r\x1b[K{PROMPT}{testo}")?;
+            if cursore < riga.len() {
+                write!(stdout, "\x1b[{}D", riga.len() - cursore)?;
+            }
+            stdout.flush()
+        };
+
+        ridisegna(&mut stdout, &riga, cursore)?;
+        loop {
+            match leggi_tasto(&mut stdin)? {
+                Tasto::Carattere(carattere) => {
+                    riga.insert(cursore, carattere);
+                    cursore += 1;
+                }
+                Tasto::Invio => {
+                    writeln!(stdout)?;
+                    return Ok(Some(riga.into_iter().collect()));
+                }
+                Tasto::FineInput if riga.is_empty() => {
+                    writeln!(stdout)?;
+                    return Ok(None);
+                }
+                Tasto::Interrompi => {
+                    writeln!(stdout, "^C")?;
+                    return Ok(Some(String::new()));
+                }
+                Tasto::Cancella if cursore > 0 => {
+                    cursore -= 1;
+                    riga.remove(cursore);
+                }
+                Tasto::CancellaAvanti if cursore < riga.len() => {
+                    riga.remove(cursore);
+                }
+                Tasto::Sinistra if cursore > 0 => cursore -= 1,
+                Tasto::Destra if cursore < riga.len() => cursore += 1,
+                Tasto::Inizio => cursore = 0,
+                Tasto::Fine => cursore = riga.len(),
+                Tasto::Su if indice > 0 => {
+                    if indice == self.storia.len() {
+                        bozza = riga.clone();
+                    }
+                    indice -= 1;
+                    riga = self.storia[indice].chars().collect();
+                    cursore = riga.len();
+                }
+                Tasto::Giu if indice < self.storia.len() => {
+                    indice += 1;
+                    riga = match self.storia.get(indice) {
+                        Some(comando) => comando.chars().collect(),
+                        None => bozza.clone(),
+                    };
+                    cursore = riga.len();
+                }
+                Tasto::Tab => {
+                    let prima: String = riga[..cursore].iter().collect();
+                    let (completata, alternative) = completa(&prima, comandi, colori);
+                    if !alternative.is_empty() {
+                        writeln!(stdout)?;
+                        writeln!(stdout, "{}", alternative.join("  "))?;
+                    }
+                    let completata: Vec<char> = completata.chars().collect();
+                    cursore = completata.len();
+                    riga.splice(..prima.chars().count(), completata);
+                }
+                _ => (),
+            }
+            ridisegna(&mut stdout, &riga, cursore)?;
+        }
+    }
+}
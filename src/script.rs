@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::{cerca_comando, esegui, tokenizza, Esito, Formato, Piano};
+
+/// Direttive gestite da `Interprete`, oltre ai comandi di `esegui`
+pub const DIRETTIVE: [&str; 5] = ["include", "define", "end", "formato", "expect"];
+
+/// Comandi verificabili con `expect`: non modificano il piano e producono al più una riga
+pub const VERIFICABILI: [&str; 8] = ["?", "b", "B", "t", "L", "n", "w", "g"];
+
+/// Macro: una sequenza di comandi con dei parametri, sostituiti ad ogni invocazione
+#[derive(PartialEq, Debug)]
+struct Macro {
+    parametri: Vec<String>,
+    corpo: Vec<String>,
+}
+
+/// Interprete dei comandi: oltre ai comandi di `esegui` gestisce le direttive
+/// - `include file` - esegue i comandi contenuti in `file`, relativo al file che lo include
+/// - `define nome p1 p2 ...` ... `end` - definisce la macro `nome` con parametri `p1 p2 ...`,
+///   nel corpo ogni parte `$p1` viene sostituita dall'argomento corrispondente
+/// - `nome a1 a2 ...` - invoca la macro `nome`
+/// - `formato testo|json` - imposta il formato dell'output dei comandi successivi
+/// - `expect comando argomenti = risultato` - verifica che il comando (`?`, `b`, `B`, `t`,
+///   `L`, `n`, `w` o `g`) produca `risultato`, o nessun output se `risultato` è `nessuno`,
+///   senza stampare nulla; se non lo produce è un errore
+pub struct Interprete {
+    formato: Formato,
+    macro_: HashMap<String, Macro>,
+    /// macro in corso di definizione, fino al prossimo `end`
+    definizione: Option<(String, Macro)>,
+    /// cartella rispetto a cui sono risolte le inclusioni dell'input principale
+    cartella: PathBuf,
+    /// file in corso di esecuzione, per individuare le inclusioni cicliche
+    inclusioni: Vec<PathBuf>,
+    /// macro in corso di espansione, per individuare le macro ricorsive
+    espansioni: Vec<String>,
+}
+
+impl Interprete {
+    /// Crea un nuovo interprete senza macro
+    ///
+    /// # Arguments
+    /// * `cartella` - cartella rispetto a cui risolvere le inclusioni
+    pub fn new(cartella: &Path) -> Self {
+        Self {
+            formato: Formato::Testo,
+            macro_: HashMap::new(),
+            definizione: None,
+            cartella: cartella.to_path_buf(),
+            inclusioni: Vec::new(),
+            espansioni: Vec::new(),
+        }
+    }
+
+    /// Crea un nuovo interprete senza macro per l'input principale letto dal file `file`:
+    /// le inclusioni sono relative alla sua cartella e includere `file` stesso è
+    /// un'inclusione ciclica
+    ///
+    /// # Arguments
+    /// * `file` - file dell'input principale
+    pub fn per_file(file: &Path) -> Self {
+        let mut interprete = Self::new(file.parent().unwrap_or(Path::new(".")));
+        interprete.inclusioni.extend(file.canonicalize().ok());
+        interprete
+    }
+
+    /// Esegue una riga di input: una direttiva, l'invocazione di una macro o un comando
+    ///
+    /// # Arguments
+    /// * `piano` - piano su cui eseguire i comandi
+    /// * `riga` - riga di input
+    /// * `logger` - funzione che riceve ogni riga di output
+    ///
+    /// # Returns
+    /// * `Ok(Esito)` - se la riga è stata eseguita
+    /// * `Err(String)` - la descrizione dell'errore, preceduta dal file e dalla riga
+    ///   o dalla macro in cui è avvenuto
+    pub fn esegui(
+        &mut self,
+        piano: &mut Piano,
+        riga: &str,
+        logger: &mut dyn FnMut(String),
+    ) -> Result<Esito, String> {
+        let parti = tokenizza(riga);
+
+        if self.definizione.is_some() {
+            match parti.as_slice() {
+                ["end"] => {
+                    let (nome, definizione) = self.definizione.take().unwrap();
+                    self.macro_.insert(nome, definizione);
+                }
+                ["define", ..] => return Err(String::from("define annidata in un'altra macro")),
+                [] => (),
+                _ => {
+                    let (.., definizione) = self.definizione.as_mut().unwrap();
+                    definizione.corpo.push(parti.join(" "));
+                }
+            }
+            return Ok(Esito::Continua);
+        }
+
+        match parti.as_slice() {
+            ["include", file] => self._includi(piano, file, logger),
+            ["include", ..] => Err(String::from("input non valido")),
+            ["define", nome, parametri @ ..] => {
+                if cerca_comando(nome).is_some() || DIRETTIVE.contains(nome) {
+                    return Err(format!("macro {nome} con il nome di un comando"));
+                }
+                self.definizione = Some((
+                    String::from(*nome),
+                    Macro {
+                        parametri: parametri.iter().map(|p| String::from(*p)).collect(),
+                        corpo: Vec::new(),
+                    },
+                ));
+                Ok(Esito::Continua)
+            }
+            ["end"] => Err(String::from("end senza define")),
+            ["formato", "testo"] => {
+                self.formato = Formato::Testo;
+                Ok(Esito::Continua)
+            }
+            ["formato", "json"] => {
+                self.formato = Formato::Json;
+                Ok(Esito::Continua)
+            }
+            ["formato", ..] => Err(String::from("input non valido")),
+            ["expect", verifica @ ..] => self._verifica(piano, verifica),
+            [nome, argomenti @ ..] if self.macro_.contains_key(*nome) => {
+                self._espandi(piano, nome, argomenti, logger)
+            }
+            _ => esegui(piano, riga, self.formato, logger),
+        }
+    }
+
+    /// Controlla che l'input sia terminato correttamente, da chiamare alla fine dell'input
+    ///
+    /// # Returns
+    /// * `Err(String)` - se una macro è in corso di definizione
+    pub fn termina(&mut self) -> Result<(), String> {
+        match self.definizione.take() {
+            Some((nome, ..)) => Err(format!("macro {nome} senza end")),
+            None => Ok(()),
+        }
+    }
+
+    /// Verifica che il comando in `verifica`, nella forma `comando argomenti = risultato`,
+    /// produca in formato `Testo` la riga `risultato` (nessuna riga se `nessuno`)
+    fn _verifica(&mut self, piano: &mut Piano, verifica: &[&str]) -> Result<Esito, String> {
+        let Some(uguale) = verifica.iter().position(|parte| *parte == "=") else {
+            return Err(String::from("input non valido"));
+        };
+        let (comando, atteso) = (&verifica[..uguale], &verifica[uguale + 1..]);
+
+        let verificabile = comando
+            .first()
+            .and_then(|nome| cerca_comando(nome))
+            .is_some_and(|comando| VERIFICABILI.contains(&comando.lettera));
+        if !verificabile {
+            let nome = comando.first().unwrap_or(&"");
+            return Err(format!("comando {nome} non verificabile con expect"));
+        }
+
+        let mut output = Vec::new();
+        esegui(piano, &comando.join(" "), Formato::Testo, &mut |s| {
+            output.push(s)
+        })?;
+        let ottenuto = match output.join(" ") {
+            ottenuto if ottenuto.is_empty() => String::from("nessuno"),
+            ottenuto => ottenuto,
+        };
+        let atteso = atteso.join(" ");
+        if ottenuto != atteso {
+            return Err(format!(
+                "asserzione fallita: {} = {atteso} (ottenuto {ottenuto})",
+                comando.join(" ")
+            ));
+        }
+        Ok(Esito::Continua)
+    }
+
+    /// Esegue tutte le righe del file `file`
+    fn _includi(
+        &mut self,
+        piano: &mut Piano,
+        file: &str,
+        logger: &mut dyn FnMut(String),
+    ) -> Result<Esito, String> {
+        let cartella = match self.inclusioni.last() {
+            Some(incluso) => incluso.parent().unwrap_or(&self.cartella),
+            None => &self.cartella,
+        };
+        let percorso = cartella
+            .join(file)
+            .canonicalize()
+            .map_err(|_| format!("errore aprendo il file {file}"))?;
+
+        if self.inclusioni.contains(&percorso) {
+            return Err(format!("inclusione ciclica di {file}"));
+        }
+        let input = File::open(&percorso).map_err(|_| format!("errore aprendo il file {file}"))?;
+
+        self.inclusioni.push(percorso);
+        let esito = self._esegui_righe(piano, BufReader::new(input), file, logger);
+        self.inclusioni.pop();
+        esito
+    }
+
+    /// Esegue tutte le righe di `input`, proveniente dal file `file`
+    fn _esegui_righe(
+        &mut self,
+        piano: &mut Piano,
+        input: impl BufRead,
+        file: &str,
+        logger: &mut dyn FnMut(String),
+    ) -> Result<Esito, String> {
+        for (numero, riga) in input.lines().enumerate() {
+            let riga = riga.map_err(|_| format!("errore leggendo il file {file}"))?;
+            let esito = self
+                .esegui(piano, &riga, logger)
+                .map_err(|errore| format!("{file}:{}: {errore}", numero + 1))?;
+            if esito == Esito::Termina {
+                return Ok(Esito::Termina);
+            }
+        }
+        self.termina()
+            .map_err(|errore| format!("{file}: {errore}"))?;
+        Ok(Esito::Continua)
+    }
+
+    /// Esegue il corpo della macro `nome`, sostituendo i parametri con `argomenti`
+    fn _espandi(
+        &mut self,
+        piano: &mut Piano,
+        nome: &str,
+        argomenti: &[&str],
+        logger: &mut dyn FnMut(String),
+    ) -> Result<Esito, String> {
+        if self.espansioni.iter().any(|espansione| espansione == nome) {
+            return Err(format!("macro {nome} ricorsiva"));
+        }
+
+        let Macro { parametri, corpo } = &self.macro_[nome];
+        if parametri.len() != argomenti.len() {
+            return Err(format!(
+                "macro {nome} con {} argomenti invece di {}",
+                argomenti.len(),
+                parametri.len()
+            ));
+        }
+
+        let sostituzioni: HashMap<String, &str> = parametri
+            .iter()
+            .map(|parametro| format!("${parametro}"))
+            .zip(argomenti.iter().copied())
+            .collect();
+        let righe: Vec<String> = corpo
+            .iter()
+            .map(|riga| {
+                riga.split(' ')
+                    .map(|parte| *sostituzioni.get(parte).unwrap_or(&parte))
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+            })
+            .collect();
+
+        self.espansioni.push(String::from(nome));
+        let mut esito = Ok(Esito::Continua);
+        for riga in righe {
+            esito = self
+                .esegui(piano, &riga, logger)
+                .map_err(|errore| format!("macro {nome}: {errore}"));
+            if esito != Ok(Esito::Continua) {
+                break;
+            }
+        }
+        self.espansioni.pop();
+        esito
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+
+use crate::archivio::{Archivio, HashBlocchi};
+use crate::tavolozza::Colore;
+use crate::{Piastrella, Tinta};
+
+/// Lato di una zona del livello più basso dell'indice spaziale, in piastrelle (potenza di 2)
+const LATO: i64 = 64;
+
+/// Zone di un livello contenute, per lato, in una zona del livello superiore (potenza di 2)
+const RAMI: i64 = 8;
+
+/// Numero di livelli dell'indice spaziale: con zone di lato `LATO * RAMI^9` = 2^33
+/// il livello più alto ha al più 2x2 zone
+const LIVELLI: usize = 10;
+
+/// Riepilogo delle piastrelle accese di una parte del piano
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct Riepilogo {
+    /// numero di piastrelle accese
+    pub piastrelle: usize,
+    /// intensità totale delle piastrelle accese
+    pub intensita: u64,
+    /// numero di piastrelle di ciascun colore presente, in un ordine qualsiasi
+    pub colori: Vec<(Colore, usize)>,
+}
+
+impl Riepilogo {
+    /// Conta una piastrella accesa con la tinta `tinta`
+    fn aggiungi(&mut self, tinta: &Tinta) {
+        self.piastrelle += 1;
+        self.intensita += tinta.intensita as u64;
+        match self.colori.iter_mut().find(|(c, _)| *c == tinta.colore) {
+            Some((_, n)) => *n += 1,
+            None => self.colori.push((tinta.colore, 1)),
+        }
+    }
+
+    /// Toglie una piastrella accesa con la tinta `tinta`, già contata
+    fn togli(&mut self, tinta: &Tinta) {
+        self.piastrelle -= 1;
+        self.intensita -= tinta.intensita as u64;
+        if let Some(i) = self.colori.iter().position(|(c, _)| *c == tinta.colore) {
+            self.colori[i].1 -= 1;
+            if self.colori[i].1 == 0 {
+                self.colori.swap_remove(i);
+            }
+        }
+    }
+
+    /// Aggiunge le piastrelle di un altro riepilogo
+    fn unisci(&mut self, altro: &Riepilogo) {
+        self.piastrelle += altro.piastrelle;
+        self.intensita += altro.intensita;
+        for (colore, n) in &altro.colori {
+            match self.colori.iter_mut().find(|(c, _)| c == colore) {
+                Some((_, m)) => *m += n,
+                None => self.colori.push((*colore, *n)),
+            }
+        }
+    }
+}
+
+/// Zone di un livello dell'indice spaziale, con il riepilogo delle loro piastrelle
+type Zone = HashMap<(i32, i32), Riepilogo, BuildHasherDefault<HashBlocchi>>;
+
+/// Indice spaziale delle piastrelle accese: una piramide di livelli in cui il piano è
+/// diviso in zone, di `LATO`x`LATO` piastrelle al livello 0 e di `RAMI`x`RAMI` zone del
+/// livello inferiore ai livelli successivi, ciascuna con il riepilogo delle sue piastrelle.
+/// Un rettangolo è riepilogato scendendo dal livello più alto: le zone interamente
+/// contenute sono unite con il loro riepilogo e solo quelle sul bordo sono divise, fino a
+/// visitare le piastrelle delle zone del livello 0 sul bordo. Il costo dipende quindi dal
+/// perimetro del rettangolo, non dalla sua area; le zone senza piastrelle accese non sono
+/// memorizzate né visitate
+#[derive(Default)]
+pub struct IndiceSpaziale {
+    livelli: [Zone; LIVELLI],
+}
+
+/// Rettangolo `xmin..=xmax` x `ymin..=ymax`, in i64 per calcolare i bordi delle zone
+#[derive(Clone, Copy)]
+struct Rettangolo {
+    xmin: i64,
+    xmax: i64,
+    ymin: i64,
+    ymax: i64,
+}
+
+impl Rettangolo {
+    /// Restituisce il rettangolo coperto dalla zona `(zx, zy)` del livello `livello`
+    fn zona((zx, zy): (i64, i64), livello: usize) -> Self {
+        let lato = 1 << esponente(livello);
+        Self {
+            xmin: zx * lato,
+            xmax: zx * lato + lato - 1,
+            ymin: zy * lato,
+            ymax: zy * lato + lato - 1,
+        }
+    }
+
+    /// Controlla se il rettangolo contiene interamente `altro`
+    fn contiene(&self, altro: &Rettangolo) -> bool {
+        self.xmin <= altro.xmin
+            && altro.xmax <= self.xmax
+            && self.ymin <= altro.ymin
+            && altro.ymax <= self.ymax
+    }
+
+    /// Restituisce l'intersezione, non vuota, con `altro`
+    fn interseca(&self, altro: &Rettangolo) -> Rettangolo {
+        Rettangolo {
+            xmin: self.xmin.max(altro.xmin),
+            xmax: self.xmax.min(altro.xmax),
+            ymin: self.ymin.max(altro.ymin),
+            ymax: self.ymax.min(altro.ymax),
+        }
+    }
+}
+
+/// Restituisce l'esponente del lato, in piastrelle, delle zone del livello `livello`
+fn esponente(livello: usize) -> u32 {
+    LATO.trailing_zeros() + RAMI.trailing_zeros() * livello as u32
+}
+
+/// Restituisce la zona del livello `livello` che contiene la piastrella
+fn zona(Piastrella { x, y }: &Piastrella, livello: usize) -> (i32, i32) {
+    // lo shift aritmetico arrotonda verso il basso anche le coordinate negative
+    let esponente = esponente(livello);
+    (
+        (*x as i64 >> esponente) as i32,
+        (*y as i64 >> esponente) as i32,
+    )
+}
+
+impl IndiceSpaziale {
+    /// Conta la piastrella `piastrella`, accesa con la tinta `tinta`
+    pub fn aggiungi(&mut self, piastrella: &Piastrella, tinta: &Tinta) {
+        for (livello, zone) in self.livelli.iter_mut().enumerate() {
+            zone.entry(zona(piastrella, livello))
+                .or_default()
+                .aggiungi(tinta);
+        }
+    }
+
+    /// Toglie la piastrella `piastrella`, che era accesa con la tinta `tinta`
+    pub fn togli(&mut self, piastrella: &Piastrella, tinta: &Tinta) {
+        for (livello, zone) in self.livelli.iter_mut().enumerate() {
+            let zona = zona(piastrella, livello);
+            if let Some(riepilogo) = zone.get_mut(&zona) {
+                riepilogo.togli(tinta);
+                if riepilogo.piastrelle == 0 {
+                    zone.remove(&zona);
+                }
+            }
+        }
+    }
+
+    /// Riepiloga le piastrelle accese del rettangolo `xmin..=xmax` x `ymin..=ymax`
+    ///
+    /// # Arguments
+    /// * `piastrelle` - le piastrelle indicizzate, lette per le zone sul bordo
+    /// * `xmin`, `xmax` - ascisse minima e massima del rettangolo
+    /// * `ymin`, `ymax` - ordinate minima e massima del rettangolo
+    ///
+    /// # Returns
+    /// * il riepilogo delle piastrelle accese nel rettangolo
+    pub fn riepiloga(
+        &self,
+        piastrelle: &impl Archivio,
+        (xmin, xmax): (i32, i32),
+        (ymin, ymax): (i32, i32),
+    ) -> Riepilogo {
+        let rettangolo = Rettangolo {
+            xmin: xmin as i64,
+            xmax: xmax as i64,
+            ymin: ymin as i64,
+            ymax: ymax as i64,
+        };
+        let mut result = Riepilogo::default();
+        self._riepiloga(
+            piastrelle,
+            LIVELLI - 1,
+            rettangolo,
+            &rettangolo,
+            &mut result,
+        );
+        result
+    }
+
+    /// Aggiunge a `result` le piastrelle di `rettangolo` nelle zone del livello `livello`
+    /// che intersecano `area`, una parte di `rettangolo`
+    fn _riepiloga(
+        &self,
+        piastrelle: &impl Archivio,
+        livello: usize,
+        area: Rettangolo,
+        rettangolo: &Rettangolo,
+        result: &mut Riepilogo,
+    ) {
+        let esponente = esponente(livello);
+        for zx in area.xmin >> esponente..=area.xmax >> esponente {
+            for zy in area.ymin >> esponente..=area.ymax >> esponente {
+                let Some(riepilogo) = self.livelli[livello].get(&(zx as i32, zy as i32)) else {
+                    continue;
+                };
+                let zona = Rettangolo::zona((zx, zy), livello);
+                if rettangolo.contiene(&zona) {
+                    result.unisci(riepilogo);
+                } else if livello > 0 {
+                    let interna = zona.interseca(rettangolo);
+                    self._riepiloga(piastrelle, livello - 1, interna, rettangolo, result);
+                } else {
+                    // zona sul bordo: solo le piastrelle interne al rettangolo
+                    let interna = zona.interseca(rettangolo);
+                    for x in interna.xmin..=interna.xmax {
+                        for y in interna.ymin..=interna.ymax {
+                            let piastrella = Piastrella {
+                                x: x as i32,
+                                y: y as i32,
+                            };
+                            if let Some(tinta) = piastrelle.get(&piastrella) {
+                                result.aggiungi(tinta);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
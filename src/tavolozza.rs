@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::formati::Json;
+use crate::{Applicazione, Regola, Requisito};
+
+/// Colore interno al piano: indice del suo nome nella `Tavolozza` del piano
+#[derive(Eq, Hash, PartialEq, PartialOrd, Ord, Clone, Copy, Default, Debug)]
+pub struct Colore(u32);
+
+impl Colore {
+    /// Restituisce l'indice del colore nella tavolozza, da 0
+    pub fn indice(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Tavolozza dei colori di un piano: associa ad ogni nome di colore un `Colore`,
+/// assegnati in ordine di prima apparizione. I colori non vengono mai rimossi,
+/// quindi un `Colore` resta valido per tutta la vita del piano
+#[derive(Default)]
+pub struct Tavolozza {
+    nomi: Vec<String>,
+    colori: HashMap<String, Colore>,
+}
+
+impl Tavolozza {
+    /// Restituisce il colore di nome `nome`, aggiungendolo alla tavolozza se assente
+    pub fn interna(&mut self, nome: &str) -> Colore {
+        if let Some(colore) = self.colori.get(nome) {
+            return *colore;
+        }
+        let colore = Colore(self.nomi.len() as u32);
+        self.nomi.push(String::from(nome));
+        self.colori.insert(String::from(nome), colore);
+        colore
+    }
+
+    /// Restituisce il colore di nome `nome`, senza aggiungerlo alla tavolozza
+    ///
+    /// # Returns
+    /// * `Some(Colore)` - se il colore è nella tavolozza
+    /// * `None` - se il colore non è mai stato usato dal piano
+    pub fn cerca(&self, nome: &str) -> Option<Colore> {
+        self.colori.get(nome).copied()
+    }
+
+    /// Restituisce il nome del colore `colore`
+    ///
+    /// # Panics
+    /// * se `colore` non appartiene alla tavolozza
+    pub fn nome(&self, colore: Colore) -> &str {
+        &self.nomi[colore.0 as usize]
+    }
+
+    /// Accompagna `valore` con la tavolozza, per formattarlo con i nomi dei colori
+    pub fn nomina<'a, T: ?Sized>(&'a self, valore: &'a T) -> Nominato<'a, T> {
+        Nominato {
+            valore,
+            tavolozza: self,
+        }
+    }
+}
+
+/// Valore formattato con i nomi dei colori della tavolozza (vedi `Tavolozza::nomina`)
+pub struct Nominato<'a, T: ?Sized> {
+    valore: &'a T,
+    tavolozza: &'a Tavolozza,
+}
+
+/// Formatta una regola come `colore: coeff1 col1 coeff2 col2 ...`
+impl fmt::Display for Nominato<'_, Regola> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:", self.tavolozza.nome(self.valore.colore))?;
+        for Requisito {
+            coefficiente,
+            colore,
+        } in &self.valore.requisiti
+        {
+            write!(f, " {} {}", coefficiente, self.tavolozza.nome(*colore))?;
+        }
+        Ok(())
+    }
+}
+
+/// Formatta un'applicazione come `x y colore_precedente colore regola`
+impl fmt::Display for Nominato<'_, Applicazione> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Applicazione {
+            piastrella,
+            colore_precedente,
+            colore,
+            regola,
+        } = self.valore;
+        write!(
+            f,
+            "{} {} {} {} {regola}",
+            piastrella.x,
+            piastrella.y,
+            self.tavolozza.nome(*colore_precedente),
+            self.tavolozza.nome(*colore)
+        )
+    }
+}
+
+impl Json for Nominato<'_, Requisito> {
+    fn json(&self) -> String {
+        format!(
+            "{{\"coefficiente\":{},\"colore\":{}}}",
+            self.valore.coefficiente,
+            self.tavolozza.nome(self.valore.colore).json()
+        )
+    }
+}
+
+impl Json for Nominato<'_, Regola> {
+    fn json(&self) -> String {
+        let requisiti: Vec<_> = self
+            .valore
+            .requisiti
+            .iter()
+            .map(|requisito| self.tavolozza.nomina(requisito))
+            .collect();
+        format!(
+            "{{\"colore\":{},\"requisiti\":{},\"utilizzo\":{}}}",
+            self.tavolozza.nome(self.valore.colore).json(),
+            requisiti.json(),
+            self.valore.utilizzo
+        )
+    }
+}
+
+impl Json for Nominato<'_, [Regola]> {
+    fn json(&self) -> String {
+        let regole: Vec<_> = self
+            .valore
+            .iter()
+            .map(|regola| self.tavolozza.nomina(regola))
+            .collect();
+        regole.json()
+    }
+}
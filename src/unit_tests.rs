@@ -1,3 +1,16 @@
+use crate::{Colore, Piano};
+
+/// Restituisce il colore `nome` della tavolozza di `piano`, per costruire le regole attese
+///
+/// # Panics
+/// * se il piano non ha mai usato il colore
+fn colore(piano: &Piano, nome: &str) -> Colore {
+    piano
+        .tavolozza
+        .cerca(nome)
+        .expect("colore assente dalla tavolozza")
+}
+
 /// Test per le funzioni `stato`, `colora`, `spegni`
 mod stato_colora_spegni {
     #[cfg(test)]
@@ -104,6 +117,8 @@ mod regola_stampa {
     #[cfg(test)]
     use crate::*;
 
+    use super::colore;
+
     #[test]
     fn test_regola() {
         let mut piano = Piano::new();
@@ -116,18 +131,18 @@ mod regola_stampa {
                 requisiti: vec![
                     Requisito {
                         coefficiente: 1,
-                        colore: String::from("verde")
+                        colore: colore(&piano, "verde")
                     },
                     Requisito {
                         coefficiente: 2,
-                        colore: String::from("rosso")
+                        colore: colore(&piano, "rosso")
                     },
                     Requisito {
                         coefficiente: 3,
-                        colore: String::from("fucsia")
+                        colore: colore(&piano, "fucsia")
                     },
                 ],
-                colore: String::from("rosso"),
+                colore: colore(&piano, "rosso"),
                 utilizzo: 0
             }
         );
@@ -139,9 +154,9 @@ mod regola_stampa {
             Regola {
                 requisiti: vec![Requisito {
                     coefficiente: 6,
-                    colore: String::from("giallo")
+                    colore: colore(&piano, "giallo")
                 },],
-                colore: String::from("verde"),
+                colore: colore(&piano, "verde"),
                 utilizzo: 0
             }
         );
@@ -198,6 +213,48 @@ mod regola_stampa {
         let mut piano = Piano::new();
         piano.regola(String::from("rosso uno verde 3 blu"));
     }
+
+    #[test]
+    #[should_panic(expected = "regola invalida (coefficiente nullo per verde)")]
+    fn test_regola_invalida7() {
+        let mut piano = Piano::new();
+        piano.regola(String::from("rosso 0 verde 3 blu"));
+    }
+
+    #[test]
+    #[should_panic(expected = "regola invalida (colore verde ripetuto)")]
+    fn test_regola_invalida8() {
+        let mut piano = Piano::new();
+        piano.regola(String::from("rosso 1 verde 3 blu 2 verde"));
+    }
+
+    #[test]
+    #[should_panic(expected = "regola invalida (somma coefficienti 9 maggiore di 8)")]
+    fn test_regola_invalida9() {
+        let mut piano = Piano::new();
+        piano.regola(String::from("rosso 5 verde 4 blu"));
+    }
+
+    #[test]
+    fn test_valida_regola() {
+        let piano = Piano::new();
+        assert!(piano.valida_regola("rosso 8 verde").is_ok());
+        assert!(piano.valida_regola("rosso 4 verde 4 blu").is_ok());
+        assert_eq!(
+            piano.valida_regola("rosso 9 verde"),
+            Err(String::from(
+                "regola invalida (somma coefficienti 9 maggiore di 8)"
+            ))
+        );
+        assert_eq!(
+            piano.valida_regola("rosso"),
+            Err(String::from("regola invalida (mancanza coefficienti)"))
+        );
+        assert_eq!(
+            piano.valida_regola("rosso -1 verde"),
+            Err(String::from("regola invalida (coefficiente invalido)"))
+        );
+    }
 }
 
 /// Test per le funzioni `_blocco_generico`, `blocco`, `blocco_omogeneo`
@@ -324,7 +381,8 @@ mod blocco {
     }
 }
 
-/// Test per le funzioni `_simula_propagazione`, `propaga`, `propaga_blocco`
+/// Test per le funzioni `_simula_propagazione`, `spiega`, `propaga`, `anteprima_blocco`,
+/// `propaga_blocco`, `_ordina_blocco`, `propaga_blocco_sequenziale`
 mod propaga {
     #[cfg(test)]
     use crate::*;
@@ -452,6 +510,201 @@ mod propaga {
         );
     }
 
+    #[test]
+    fn test_spiega() {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("g"), 1);
+        piano.colora(0, 2, String::from("b"), 2);
+        piano.colora(1, 1, String::from("g"), 3);
+        piano.colora(2, 0, String::from("b"), 6);
+        piano.colora(2, 2, String::from("b"), 7);
+
+        piano.regola(String::from("z 2 g 1 b"));
+        piano.regola(String::from("w 1 g 2 b"));
+        piano.regola(String::from("y 1 b 1 r"));
+
+        assert_eq!(
+            piano.spiega(1, 1),
+            String::from(
+                "(\nintorno: b 3 g 1\n\
+                0 z: 2 g 1 b -> manca 2 g (presenti 1)\n\
+                1 w: 1 g 2 b -> applicabile\n\
+                2 y: 1 b 1 r -> manca 1 r (presenti 0)\n\
+                risultato: 1 w\n)"
+            )
+        );
+        assert_eq!(
+            piano.spiega(5, 5),
+            String::from(
+                "(\nintorno:\n\
+                0 z: 2 g 1 b -> manca 2 g (presenti 0)\n\
+                1 w: 1 g 2 b -> manca 1 g (presenti 0)\n\
+                2 y: 1 b 1 r -> manca 1 b (presenti 0)\n\
+                risultato: nessuna\n)"
+            )
+        );
+
+        // la spiegazione non modifica il piano
+        assert_eq!(
+            piano.stato(1, 1),
+            Some(Colorazione {
+                colore: String::from("g"),
+                intensita: 3
+            })
+        );
+        assert!(piano
+            .regole
+            .iter()
+            .all(|Regola { utilizzo, .. }| *utilizzo == 0));
+    }
+
+    #[test]
+    fn test_anteprima_blocco() {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("g"), 1);
+        piano.colora(0, 2, String::from("b"), 2);
+        piano.colora(1, 1, String::from("g"), 3);
+        piano.colora(1, 3, String::from("r"), 4);
+        piano.colora(1, 4, String::from("b"), 5);
+        piano.colora(2, 0, String::from("b"), 6);
+        piano.colora(2, 2, String::from("b"), 7);
+        piano.colora(3, 0, String::from("b"), 8);
+        piano.colora(3, 1, String::from("r"), 9);
+        piano.colora(3, 2, String::from("b"), 10);
+        piano.colora(3, 4, String::from("r"), 11);
+        piano.colora(4, 4, String::from("r"), 12);
+
+        piano.regola(String::from("z 2 g 1 b"));
+        piano.regola(String::from("w 1 g 2 b"));
+        piano.regola(String::from("y 1 b 1 r"));
+        piano.regola(String::from("g 2 b 1 r"));
+        piano.regola(String::from("t 1 b 1 g 1 r"));
+
+        let tavolozza = &piano.tavolozza;
+        let applicazione = |x, y, colore_precedente, colore, regola| Applicazione {
+            piastrella: Piastrella { x, y },
+            colore_precedente: tavolozza.cerca(colore_precedente).unwrap(),
+            colore: tavolozza.cerca(colore).unwrap(),
+            regola,
+        };
+        let expected = vec![
+            applicazione(1, 1, "g", "w", 1),
+            applicazione(2, 0, "b", "y", 2),
+            applicazione(2, 2, "b", "y", 2),
+            applicazione(3, 0, "b", "y", 2),
+            applicazione(3, 2, "b", "y", 2),
+        ];
+        assert_eq!(piano.anteprima_blocco(1, 1), expected);
+        assert_eq!(
+            piano.stampa_anteprima_blocco(1, 1),
+            String::from("(\n1 1 g w 1\n2 0 b y 2\n2 2 b y 2\n3 0 b y 2\n3 2 b y 2\n)")
+        );
+
+        // l'anteprima non modifica il piano
+        assert!(piano
+            .regole
+            .iter()
+            .all(|Regola { utilizzo, .. }| *utilizzo == 0));
+        assert_eq!(
+            piano.stato(1, 1),
+            Some(Colorazione {
+                colore: String::from("g"),
+                intensita: 3
+            })
+        );
+
+        piano.propaga_blocco(1, 1);
+        for Applicazione {
+            piastrella: Piastrella { x, y },
+            colore,
+            ..
+        } in expected
+        {
+            assert_eq!(
+                piano.stato(x, y).unwrap().colore,
+                piano.tavolozza.nome(colore)
+            );
+        }
+        assert_eq!(piano.anteprima_blocco(7, 7), vec![]);
+    }
+
+    #[test]
+    fn test_ordina_blocco() {
+        let mut piano = Piano::new();
+        piano.colora(1, 1, String::from("a"), 1);
+        piano.colora(0, 0, String::from("a"), 1);
+        piano.colora(2, 0, String::from("a"), 1);
+        piano.colora(0, 2, String::from("a"), 1);
+        piano.colora(5, 5, String::from("a"), 1);
+
+        let piastrelle = |coordinate: &[(i32, i32)]| {
+            coordinate
+                .iter()
+                .map(|(x, y)| Piastrella { x: *x, y: *y })
+                .collect::<Vec<Piastrella>>()
+        };
+
+        assert_eq!(
+            piano._ordina_blocco(1, 1, Ordine::Bfs),
+            piastrelle(&[(1, 1), (0, 0), (0, 2), (2, 0)])
+        );
+        assert_eq!(
+            piano._ordina_blocco(0, 2, Ordine::Bfs),
+            piastrelle(&[(0, 2), (1, 1), (0, 0), (2, 0)])
+        );
+        assert_eq!(
+            piano._ordina_blocco(0, 2, Ordine::Righe),
+            piastrelle(&[(0, 0), (2, 0), (1, 1), (0, 2)])
+        );
+        assert_eq!(
+            piano._ordina_blocco(0, 2, Ordine::Coordinate),
+            piastrelle(&[(0, 0), (0, 2), (1, 1), (2, 0)])
+        );
+        assert_eq!(piano._ordina_blocco(3, 3, Ordine::Bfs), vec![]);
+    }
+
+    #[test]
+    fn test_propaga_blocco_sequenziale() {
+        let piano_riga = || {
+            let mut piano = Piano::new();
+            piano.colora(0, 0, String::from("a"), 1);
+            piano.colora(1, 0, String::from("a"), 2);
+            piano.colora(2, 0, String::from("a"), 3);
+            piano.colora(3, 0, String::from("b"), 4);
+            piano.regola(String::from("b 1 b"));
+            piano
+        };
+        let colori = |piano: &Piano| {
+            (0..4)
+                .map(|x| piano.stato(x, 0).unwrap().colore)
+                .collect::<Vec<String>>()
+        };
+
+        // sincrona: solo la piastrella adiacente a (3,0) cambia colore
+        let mut piano = piano_riga();
+        piano.propaga_blocco(3, 0);
+        assert_eq!(colori(&piano), vec!["a", "a", "b", "b"]);
+        assert_eq!(piano.regole[0].utilizzo, 1);
+
+        // sequenziale in ampiezza da (3,0): il colore si propaga lungo la riga
+        let mut piano = piano_riga();
+        piano.propaga_blocco_sequenziale(3, 0, Ordine::Bfs);
+        assert_eq!(colori(&piano), vec!["b", "b", "b", "b"]);
+        assert_eq!(piano.regole[0].utilizzo, 3);
+        assert_eq!(piano.stato(0, 0).unwrap().intensita, 1);
+
+        // sequenziale per coordinate: (0,0) e (1,0) sono visitate prima che cambi (2,0)
+        let mut piano = piano_riga();
+        piano.propaga_blocco_sequenziale(3, 0, Ordine::Coordinate);
+        assert_eq!(colori(&piano), vec!["a", "a", "b", "b"]);
+        assert_eq!(piano.regole[0].utilizzo, 2);
+
+        let mut piano = piano_riga();
+        piano.propaga_blocco_sequenziale(3, 0, Ordine::Righe);
+        assert_eq!(colori(&piano), vec!["a", "a", "b", "b"]);
+        assert_eq!(piano.regole[0].utilizzo, 2);
+    }
+
     #[test]
     fn test_propaga_vuoto() {
         let mut piano = Piano::new();
@@ -478,6 +731,8 @@ mod ordina {
     #[cfg(test)]
     use crate::*;
 
+    use super::colore;
+
     #[test]
     fn test_ordina() {
         let mut piano = Piano::new();
@@ -496,25 +751,25 @@ mod ordina {
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("x")
+                        colore: colore(&piano, "x")
                     }],
-                    colore: String::from("x"),
+                    colore: colore(&piano, "x"),
                     utilizzo: 1
                 },
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("y")
+                        colore: colore(&piano, "y")
                     }],
-                    colore: String::from("y"),
+                    colore: colore(&piano, "y"),
                     utilizzo: 0
                 },
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("z")
+                        colore: colore(&piano, "z")
                     }],
-                    colore: String::from("z"),
+                    colore: colore(&piano, "z"),
                     utilizzo: 0
                 }
             ]
@@ -527,25 +782,25 @@ mod ordina {
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("y")
+                        colore: colore(&piano, "y")
                     }],
-                    colore: String::from("y"),
+                    colore: colore(&piano, "y"),
                     utilizzo: 0
                 },
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("z")
+                        colore: colore(&piano, "z")
                     }],
-                    colore: String::from("z"),
+                    colore: colore(&piano, "z"),
                     utilizzo: 0
                 },
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("x")
+                        colore: colore(&piano, "x")
                     }],
-                    colore: String::from("x"),
+                    colore: colore(&piano, "x"),
                     utilizzo: 1
                 },
             ]
@@ -558,25 +813,25 @@ mod ordina {
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("y")
+                        colore: colore(&piano, "y")
                     }],
-                    colore: String::from("y"),
+                    colore: colore(&piano, "y"),
                     utilizzo: 1
                 },
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("z")
+                        colore: colore(&piano, "z")
                     }],
-                    colore: String::from("z"),
+                    colore: colore(&piano, "z"),
                     utilizzo: 0
                 },
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("x")
+                        colore: colore(&piano, "x")
                     }],
-                    colore: String::from("x"),
+                    colore: colore(&piano, "x"),
                     utilizzo: 1
                 },
             ]
@@ -589,25 +844,25 @@ mod ordina {
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("z")
+                        colore: colore(&piano, "z")
                     }],
-                    colore: String::from("z"),
+                    colore: colore(&piano, "z"),
                     utilizzo: 0
                 },
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("y")
+                        colore: colore(&piano, "y")
                     }],
-                    colore: String::from("y"),
+                    colore: colore(&piano, "y"),
                     utilizzo: 1
                 },
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("x")
+                        colore: colore(&piano, "x")
                     }],
-                    colore: String::from("x"),
+                    colore: colore(&piano, "x"),
                     utilizzo: 1
                 },
             ]
@@ -620,25 +875,25 @@ mod ordina {
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("z")
+                        colore: colore(&piano, "z")
                     }],
-                    colore: String::from("z"),
+                    colore: colore(&piano, "z"),
                     utilizzo: 0
                 },
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("y")
+                        colore: colore(&piano, "y")
                     }],
-                    colore: String::from("y"),
+                    colore: colore(&piano, "y"),
                     utilizzo: 2
                 },
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("x")
+                        colore: colore(&piano, "x")
                     }],
-                    colore: String::from("x"),
+                    colore: colore(&piano, "x"),
                     utilizzo: 1
                 },
             ]
@@ -651,25 +906,25 @@ mod ordina {
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("z")
+                        colore: colore(&piano, "z")
                     }],
-                    colore: String::from("z"),
+                    colore: colore(&piano, "z"),
                     utilizzo: 0
                 },
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("x")
+                        colore: colore(&piano, "x")
                     }],
-                    colore: String::from("x"),
+                    colore: colore(&piano, "x"),
                     utilizzo: 1
                 },
                 Regola {
                     requisiti: vec![Requisito {
                         coefficiente: 1,
-                        colore: String::from("y")
+                        colore: colore(&piano, "y")
                     }],
-                    colore: String::from("y"),
+                    colore: colore(&piano, "y"),
                     utilizzo: 2
                 },
             ]
@@ -705,7 +960,7 @@ mod ordina {
             .map(
                 |Regola {
                      utilizzo, colore, ..
-                 }| (*utilizzo, colore.clone())
+                 }| (*utilizzo, String::from(piano.tavolozza.nome(*colore)))
             )
             .eq(vec![
                 (0, String::from('z')),
@@ -722,7 +977,7 @@ mod ordina {
             .map(
                 |Regola {
                      utilizzo, colore, ..
-                 }| (*utilizzo, colore.clone())
+                 }| (*utilizzo, String::from(piano.tavolozza.nome(*colore)))
             )
             .eq(vec![
                 (0, String::from('z')),
@@ -739,7 +994,7 @@ mod ordina {
             .map(
                 |Regola {
                      utilizzo, colore, ..
-                 }| (*utilizzo, colore.clone())
+                 }| (*utilizzo, String::from(piano.tavolozza.nome(*colore)))
             )
             .eq(vec![
                 (0, String::from('z')),
@@ -750,3 +1005,2182 @@ mod ordina {
             ]));
     }
 }
+
+/// Test per le funzioni `rimuovi_regola`, `rimuovi_regola_testo`, `sostituisci_regola`,
+/// `azzera_utilizzo`, `stampa_utilizzo`
+mod gestione_regole {
+    #[cfg(test)]
+    use crate::*;
+
+    use super::colore;
+
+    fn piano_regole() -> Piano {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("x"), 1);
+        piano.colora(0, 1, String::from("y"), 3);
+
+        piano.regola(String::from("x 1 x"));
+        piano.regola(String::from("y 1 y"));
+        piano.regola(String::from("z 1 x 1 y"));
+        piano
+    }
+
+    #[test]
+    fn test_rimuovi_regola() {
+        let mut piano = piano_regole();
+
+        let rimossa = piano.rimuovi_regola(1);
+        assert_eq!(
+            rimossa,
+            Some(Regola {
+                requisiti: vec![Requisito {
+                    coefficiente: 1,
+                    colore: colore(&piano, "y")
+                }],
+                colore: colore(&piano, "y"),
+                utilizzo: 0
+            })
+        );
+        assert_eq!(piano.stampa(), String::from("(\nx: 1 x\nz: 1 x 1 y\n)"));
+
+        assert_eq!(piano.rimuovi_regola(2), None);
+        assert_eq!(piano.regole.len(), 2);
+    }
+
+    #[test]
+    fn test_rimuovi_regola_testo() {
+        let mut piano = piano_regole();
+
+        assert!(piano
+            .rimuovi_regola_testo(String::from("z 1 x 1 y"))
+            .is_some());
+        assert_eq!(piano.stampa(), String::from("(\nx: 1 x\ny: 1 y\n)"));
+
+        assert!(piano
+            .rimuovi_regola_testo(String::from("z 1 y 1 x"))
+            .is_none());
+        assert!(piano.rimuovi_regola_testo(String::from("y 2 y")).is_none());
+        assert_eq!(piano.regole.len(), 2);
+    }
+
+    #[test]
+    fn test_sostituisci_regola() {
+        let mut piano = piano_regole();
+
+        piano.propaga(1, 0);
+        assert_eq!(piano.regole[0].utilizzo, 1);
+
+        assert!(piano.sostituisci_regola(0, String::from("w 2 y")));
+        assert_eq!(
+            piano.regole[0],
+            Regola {
+                requisiti: vec![Requisito {
+                    coefficiente: 2,
+                    colore: colore(&piano, "y")
+                }],
+                colore: colore(&piano, "w"),
+                utilizzo: 1
+            }
+        );
+
+        assert!(!piano.sostituisci_regola(3, String::from("w 2 y")));
+    }
+
+    #[test]
+    fn test_azzera_stampa_utilizzo() {
+        let mut piano = piano_regole();
+
+        piano.propaga(1, 0);
+        piano.propaga(1, 0);
+        assert_eq!(
+            piano.stampa_utilizzo(),
+            String::from("(\n0 2 x: 1 x\n1 0 y: 1 y\n2 0 z: 1 x 1 y\n)")
+        );
+
+        piano.azzera_utilizzo();
+        assert_eq!(
+            piano.stampa_utilizzo(),
+            String::from("(\n0 0 x: 1 x\n1 0 y: 1 y\n2 0 z: 1 x 1 y\n)")
+        );
+    }
+}
+
+/// Test per le funzioni `analizza_regole`, `stampa_analisi`
+mod analisi {
+    #[cfg(test)]
+    use crate::*;
+
+    #[test]
+    fn test_analizza_regole() {
+        let mut piano = Piano::new();
+
+        piano.regola(String::from("x 1 a 1 b"));
+        piano.regola(String::from("y 2 a 1 b 1 c"));
+        piano.regola(String::from("x 1 b 1 a"));
+        piano.regola(String::from("z 1 a"));
+        piano.regola(String::from("w 2 c"));
+        piano.regola(String::from("y 1 c 1 b 2 a"));
+
+        assert_eq!(
+            piano.analizza_regole(),
+            vec![
+                Anomalia::Oscurata { indice: 1, da: 0 },
+                Anomalia::Duplicata {
+                    indice: 2,
+                    originale: 0
+                },
+                Anomalia::Duplicata {
+                    indice: 5,
+                    originale: 1
+                },
+            ]
+        );
+        assert_eq!(
+            piano.stampa_analisi(),
+            String::from("(\n1 oscurata da 0\n2 duplicata di 0\n5 duplicata di 1\n)")
+        );
+    }
+
+    #[test]
+    fn test_analizza_vuoto() {
+        let mut piano = Piano::new();
+        assert!(piano.analizza_regole().is_empty());
+
+        piano.regola(String::from("x 1 a"));
+        piano.regola(String::from("y 1 b"));
+        piano.regola(String::from("z 2 b"));
+        assert_eq!(
+            piano.analizza_regole(),
+            vec![Anomalia::Oscurata { indice: 2, da: 1 }]
+        );
+        assert_eq!(
+            piano.stampa_analisi(),
+            String::from("(\n2 oscurata da 1\n)")
+        );
+    }
+}
+
+/// Test per le funzioni `esegui`, `tokenizza`, `cerca_comando`, `aiuto`
+mod esegui {
+    #[cfg(test)]
+    use crate::*;
+
+    fn esegui_righe(piano: &mut Piano, righe: &[&str]) -> (Vec<String>, Vec<String>) {
+        let mut output = Vec::new();
+        let mut errori = Vec::new();
+        for riga in righe {
+            if let Err(errore) = esegui(piano, riga, Formato::Testo, &mut |s| output.push(s)) {
+                errori.push(errore);
+            }
+        }
+        (output, errori)
+    }
+
+    #[test]
+    fn test_esegui() {
+        let mut piano = Piano::new();
+
+        let (output, errori) = esegui_righe(
+            &mut piano,
+            &["C 0 0 r 3", "C 1 1 g 2", "? 0 0", "b 1 1", "r g 1 r", "s"],
+        );
+        assert_eq!(output, vec!["r 3", "5", "(\ng: 1 r\n)"]);
+        assert!(errori.is_empty());
+
+        assert_eq!(
+            esegui(&mut piano, "q", Formato::Testo, &mut |_| ()),
+            Ok(Esito::Termina)
+        );
+    }
+
+    #[test]
+    fn test_esegui_errori() {
+        let mut piano = Piano::new();
+
+        let (output, errori) = esegui_righe(
+            &mut piano,
+            &[
+                "C 0 0 r",
+                "C 0 x r 1",
+                "C 0 0 r 0",
+                "r g 9 r",
+                "r g 1",
+                "M x g 1 r",
+                "A 0 0 diagonale",
+                "? 0 0",
+                "s",
+            ],
+        );
+        assert_eq!(output, vec!["(\n)"]);
+        assert_eq!(
+            errori,
+            vec![
+                "input non valido",
+                "input non valido",
+                "intensità non valida",
+                "regola invalida (somma coefficienti 9 maggiore di 8)",
+                "regola invalida (mancanza coefficienti)",
+                "input non valido",
+                "input non valido",
+            ]
+        );
+        assert_eq!(piano.piastrelle.len(), 0);
+    }
+
+    #[test]
+    fn test_esegui_nomi_estesi() {
+        let brevi = [
+            "C 0 0 r 3",
+            "C 1 1 g 2",
+            "C 0 1 g 1",
+            "r z 1 g",
+            "? 0 0",
+            "b 0 0",
+            "B 0 0",
+            "p 1 0",
+            "P 0 0",
+            "o",
+            "s",
+            "t 0 0 NN EE",
+            "L 0 0 1 1",
+            "S 0 0",
+            "? 0 0",
+        ];
+        let estesi = [
+            "colora 0 0 r 3",
+            "colora 1 1 g 2",
+            "colora 0 1 g 1",
+            "regola z 1 g",
+            "stato 0 0",
+            "blocco 0 0",
+            "blocco_omogeneo 0 0",
+            "propaga 1 0",
+            "propaga_blocco 0 0",
+            "ordina",
+            "stampa",
+            "pista 0 0 NN EE",
+            "lung 0 0 1 1",
+            "spegni 0 0",
+            "stato 0 0",
+        ];
+
+        let mut piano_brevi = Piano::new();
+        let mut piano_estesi = Piano::new();
+        let (output_brevi, errori_brevi) = esegui_righe(&mut piano_brevi, &brevi);
+        let (output_estesi, errori_estesi) = esegui_righe(&mut piano_estesi, &estesi);
+
+        assert!(errori_brevi.is_empty() && errori_estesi.is_empty());
+        assert_eq!(output_brevi.len(), 6);
+        assert_eq!(output_brevi, output_estesi);
+        assert_eq!(piano_brevi.colorazioni(), piano_estesi.colorazioni());
+        assert_eq!(piano_brevi.regole, piano_estesi.regole);
+
+        assert_eq!(
+            esegui(&mut piano_estesi, "esci", Formato::Testo, &mut |_| ()),
+            Ok(Esito::Termina)
+        );
+    }
+
+    #[test]
+    fn test_cerca_comando() {
+        assert_eq!(cerca_comando("B").unwrap().nome, "blocco_omogeneo");
+        assert_eq!(cerca_comando("blocco_omogeneo").unwrap().lettera, "B");
+        assert_eq!(cerca_comando("lung").unwrap().lettera, "L");
+        assert!(cerca_comando("x").is_none());
+        assert!(cerca_comando("Colora").is_none());
+
+        // forme brevi ed estese sono tutte distinte
+        let nomi: HashSet<&str> = COMANDI
+            .iter()
+            .flat_map(|comando| [comando.lettera, comando.nome])
+            .collect();
+        assert_eq!(nomi.len(), COMANDI.len() * 2);
+    }
+
+    #[test]
+    fn test_esegui_json() {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("r"), 3);
+
+        let mut output = Vec::new();
+        for riga in [
+            "? 0 0",
+            "stato 1 1",
+            "C 1 1 r 1",
+            "lung 0 0 1 1",
+            "pista 0 0 EE",
+            "s",
+        ] {
+            esegui(&mut piano, riga, Formato::Json, &mut |s| output.push(s)).unwrap();
+        }
+        assert_eq!(
+            output,
+            vec![
+                r#"{"comando":"?","argomenti":["0","0"],"risultato":{"colore":"r","intensita":3}}"#,
+                r#"{"comando":"?","argomenti":["1","1"],"risultato":null}"#,
+                r#"{"comando":"L","argomenti":["0","0","1","1"],"risultato":4}"#,
+                r#"{"comando":"t","argomenti":["0","0","EE"],"risultato":null}"#,
+                r#"{"comando":"s","argomenti":[],"risultato":[]}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!("a\"b\\c\nd\u{1}".json(), r#""a\"b\\c\nd\u0001""#);
+        assert_eq!(Some(3u32).json(), "3");
+        assert_eq!(None::<u32>.json(), "null");
+        assert_eq!(["a", "b"].json(), r#"["a","b"]"#);
+        assert_eq!(Vec::<u32>::new().json(), "[]");
+
+        let mut piano = Piano::new();
+        piano.regola(String::from("z 1 g 2 \"b"));
+        assert_eq!(
+            piano.esporta_regole(),
+            r#"[{"colore":"z","requisiti":[{"coefficiente":1,"colore":"g"},{"coefficiente":2,"colore":"\"b"}],"utilizzo":0}]"#
+        );
+    }
+
+    #[test]
+    fn test_mutazioni() {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("r"), 1);
+        piano.colora(1, 0, String::from("r"), 1);
+        piano.colora(2, 0, String::from("r"), 1);
+
+        // senza registrazione non ci sono modifiche
+        assert!(piano.modifiche().is_empty());
+
+        piano.registra_modifiche();
+        piano.spegni(0, 0);
+        piano.colora(1, 0, String::from("g"), 1);
+        piano.colora(-1, 5, String::from("b"), 4);
+        // riportate allo stato precedente: non sono modifiche
+        piano.colora(2, 0, String::from("b"), 3);
+        piano.colora(2, 0, String::from("r"), 1);
+        piano.colora(7, 7, String::from("r"), 1);
+        piano.spegni_rettangolo(7, 7, 7, 7);
+
+        assert_eq!(
+            mutazioni(&piano.modifiche()),
+            vec![
+                "~ -1 5 spenta -> b 4",
+                "~ 0 0 r 1 -> spenta",
+                "~ 1 0 r 1 -> g 1"
+            ]
+        );
+        // le modifiche già restituite non sono ripetute
+        assert!(piano.modifiche().is_empty());
+        piano.spegni_rettangolo(i32::MIN / 2, -10, i32::MAX / 2, 10);
+        assert_eq!(
+            mutazioni(&piano.modifiche()),
+            vec![
+                "~ -1 5 b 4 -> spenta",
+                "~ 1 0 g 1 -> spenta",
+                "~ 2 0 r 1 -> spenta"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenizza() {
+        assert_eq!(tokenizza("C 1 2 r 3"), vec!["C", "1", "2", "r", "3"]);
+        assert_eq!(tokenizza("  C\t1  2 r 3 \r"), vec!["C", "1", "2", "r", "3"]);
+        assert_eq!(tokenizza("? 1 2 # stato"), vec!["?", "1", "2"]);
+        assert_eq!(tokenizza("s#"), vec!["s"]);
+        assert!(tokenizza("").is_empty());
+        assert!(tokenizza(" \t\r").is_empty());
+        assert!(tokenizza("# commento").is_empty());
+    }
+
+    #[test]
+    fn test_esegui_righe_vuote() {
+        let mut piano = Piano::new();
+
+        let (output, errori) = esegui_righe(&mut piano, &["", "   ", "\r", "# C 0 0 r 1", "s"]);
+        assert_eq!(output, vec!["(\n)"]);
+        assert!(errori.is_empty());
+        assert_eq!(piano.piastrelle.len(), 0);
+
+        let (output, errori) =
+            esegui_righe(&mut piano, &["r  g 1\tr", "t 0 0  NN", "C 0 0 r 1\r", "s"]);
+        assert_eq!(output, vec!["(\ng: 1 r\n)"]);
+        assert!(errori.is_empty());
+    }
+
+    #[test]
+    fn test_aiuto() {
+        let mut output = Vec::new();
+        let mut piano = Piano::new();
+        assert_eq!(
+            esegui(&mut piano, "help", Formato::Testo, &mut |s| output.push(s)),
+            Ok(Esito::Continua)
+        );
+        assert_eq!(output, vec![aiuto()]);
+
+        for comando in [
+            "C ", "S ", "r ", "? ", "s ", "b ", "B ", "p ", "P ", "o ", "t ", "L ",
+        ] {
+            assert!(aiuto().lines().any(|riga| riga.starts_with(comando)));
+        }
+        assert_eq!(aiuto().lines().count(), COMANDI.len());
+    }
+
+    #[test]
+    fn test_colori() {
+        let mut piano = Piano::new();
+        assert!(piano.colori().is_empty());
+
+        piano.colora(0, 0, String::from("rosso"), 1);
+        piano.colora(1, 0, String::from("blu"), 1);
+        piano.colora(2, 0, String::from("rosso"), 1);
+        piano.regola(String::from("verde 1 giallo 2 blu"));
+        assert_eq!(piano.colori(), vec!["blu", "giallo", "rosso", "verde"]);
+    }
+}
+
+/// Test per le funzioni `repl::completa`, `repl::leggi_tasto`
+mod interattivo {
+    #[cfg(test)]
+    use crate::repl::*;
+
+    fn stringhe(s: &[&str]) -> Vec<String> {
+        s.iter().map(|s| String::from(*s)).collect()
+    }
+
+    #[test]
+    fn test_completa_comando() {
+        let comandi = stringhe(&["C", "S", "help", "?"]);
+        let colori = stringhe(&["rosso", "rosa", "blu"]);
+
+        assert_eq!(
+            completa("h", &comandi, &colori),
+            (String::from("help "), vec![])
+        );
+        assert_eq!(
+            completa("x", &comandi, &colori),
+            (String::from("x"), vec![])
+        );
+        assert_eq!(
+            completa("", &comandi, &colori),
+            (String::from(""), comandi.clone())
+        );
+    }
+
+    #[test]
+    fn test_completa_colore() {
+        let comandi = stringhe(&["C", "S"]);
+        let colori = stringhe(&["rosso", "rosa", "blu"]);
+
+        assert_eq!(
+            completa("C 1 2 b", &comandi, &colori),
+            (String::from("C 1 2 blu "), vec![])
+        );
+        assert_eq!(
+            completa("C 1 2 r", &comandi, &colori),
+            (String::from("C 1 2 ros"), stringhe(&["rosso", "rosa"]))
+        );
+        assert_eq!(
+            completa("C 1 2 v", &comandi, &colori),
+            (String::from("C 1 2 v"), vec![])
+        );
+    }
+
+    #[test]
+    fn test_leggi_tasto() {
+        let mut input: &[u8] = b"a\x1b[A\x1b[D\x7f\t\r\x1b[3~\xc3\xa8\x04";
+
+        let mut tasti = Vec::new();
+        loop {
+            let tasto = leggi_tasto(&mut input).unwrap();
+            if tasto == Tasto::FineInput {
+                break;
+            }
+            tasti.push(tasto);
+        }
+        assert_eq!(
+            tasti,
+            vec![
+                Tasto::Carattere('a'),
+                Tasto::Su,
+                Tasto::Sinistra,
+                Tasto::Cancella,
+                Tasto::Tab,
+                Tasto::Invio,
+                Tasto::CancellaAvanti,
+                Tasto::Carattere('è'),
+            ]
+        );
+        assert_eq!(leggi_tasto(&mut input).unwrap(), Tasto::FineInput);
+    }
+}
+
+/// Test per `script::Interprete`: inclusioni e macro
+mod script {
+    #[cfg(test)]
+    use crate::script::*;
+    #[cfg(test)]
+    use crate::*;
+    #[cfg(test)]
+    use std::path::{Path, PathBuf};
+
+    fn interpreta(
+        interprete: &mut Interprete,
+        piano: &mut Piano,
+        righe: &[&str],
+    ) -> (Vec<String>, Vec<String>) {
+        let mut output = Vec::new();
+        let mut errori = Vec::new();
+        for riga in righe {
+            if let Err(errore) = interprete.esegui(piano, riga, &mut |s| output.push(s)) {
+                errori.push(errore);
+            }
+        }
+        (output, errori)
+    }
+
+    /// Crea una cartella temporanea con i file `file` (nome e contenuto)
+    fn cartella_temporanea(nome: &str, file: &[(&str, &str)]) -> PathBuf {
+        let cartella = std::env::temp_dir().join(format!("piastrelle_{nome}"));
+        std::fs::create_dir_all(&cartella).unwrap();
+        for (nome, contenuto) in file {
+            let file = cartella.join(nome);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, contenuto).unwrap();
+        }
+        cartella
+    }
+
+    #[test]
+    fn test_expect() {
+        let mut piano = Piano::new();
+        let mut interprete = Interprete::new(Path::new("."));
+
+        let (output, errori) = interpreta(
+            &mut interprete,
+            &mut piano,
+            &[
+                "C 0 0 r 3",
+                "C 1 0 g 1",
+                "expect ? 0 0 = r 3",
+                "expect stato 0 5 = nessuno",
+                "expect b 0 0 = 4",
+                "expect B 0 0 = 3",
+                "expect t 0 0 EE = 4",
+                "expect lung 0 0 1 0 = 4",
+                "expect L 0 0 1 5 = nessuno",
+                "define verifica x y totale",
+                "expect b $x $y = $totale",
+                "end",
+                "verifica 1 0 4",
+                "verifica 1 0 5",
+                "expect ? 0 0 = r",
+                "expect ? 0 0 = nessuno",
+                "expect b 0 0 4",
+                "expect p 0 0 = 4",
+                "expect sconosciuto = 4",
+                "expect b 0 = 4",
+            ],
+        );
+        assert!(output.is_empty());
+        assert_eq!(
+            errori,
+            vec![
+                "macro verifica: asserzione fallita: b 1 0 = 5 (ottenuto 4)",
+                "asserzione fallita: ? 0 0 = r (ottenuto r 3)",
+                "asserzione fallita: ? 0 0 = nessuno (ottenuto r 3)",
+                "input non valido",
+                "comando p non verificabile con expect",
+                "comando sconosciuto non verificabile con expect",
+                "input non valido",
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "riga 4: asserzione fallita: b 0 0 = 2 (ottenuto 1)")]
+    fn test_expect_run() {
+        let cartella = cartella_temporanea(
+            "expect",
+            &[("input.in", "C 0 0 r 1\n\n# b\nexpect b 0 0 = 2\n")],
+        );
+        let input = cartella.join("input.in").display().to_string();
+        let output = cartella.join("output.out").display().to_string();
+        run(Some(input), Some(output), Traccia::Nessuna);
+    }
+
+    #[test]
+    fn test_run_su() {
+        let cartella = cartella_temporanea(
+            "run_su",
+            &[
+                ("termina.in", "C 0 0 r 1\n? 0 0\nxyz\nq\n? 0 0\n"),
+                ("errore.in", "C 0 0 r 1\n? 0 0\nS 0\n? 0 0\n"),
+            ],
+        );
+        let input = |nome: &str| Some(cartella.join(nome).display().to_string());
+
+        let mut output = Vec::new();
+        run_su(input("termina.in"), &mut output, Traccia::Nessuna);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "r 1\ncomando sconosciuto xyz, scrivi help per l'elenco dei comandi\n"
+        );
+
+        // l'output dei comandi precedenti l'errore è scritto prima del panic
+        let mut output = Vec::new();
+        let errore = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run_su(input("errore.in"), &mut output, Traccia::Righe)
+        }))
+        .unwrap_err();
+        assert_eq!(
+            errore.downcast_ref::<String>().unwrap(),
+            "riga 3: input non valido"
+        );
+        assert_eq!(String::from_utf8(output).unwrap(), "2 ? 0 0: r 1\n");
+    }
+
+    #[test]
+    fn test_run_su_include_se_stesso() {
+        let cartella = cartella_temporanea(
+            "run_su_include",
+            &[("principale.in", "C 0 0 r 1\n? 0 0\ninclude principale.in\n")],
+        );
+        let input = Some(cartella.join("principale.in").display().to_string());
+
+        // il ciclo è individuato prima di eseguire di nuovo l'input principale
+        let mut output = Vec::new();
+        let errore = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run_su(input, &mut output, Traccia::Nessuna)
+        }))
+        .unwrap_err();
+        assert_eq!(
+            errore.downcast_ref::<String>().unwrap(),
+            "riga 3: inclusione ciclica di principale.in"
+        );
+        assert_eq!(String::from_utf8(output).unwrap(), "r 1\n");
+    }
+
+    #[test]
+    fn test_macro() {
+        let mut piano = Piano::new();
+        let mut interprete = Interprete::new(Path::new("."));
+
+        let (output, errori) = interpreta(
+            &mut interprete,
+            &mut piano,
+            &[
+                "define riga y colore",
+                "C 0 $y $colore 1",
+                "",
+                "C 1 $y $colore 2 # commento",
+                "end",
+                "define mostra x y",
+                "? $x $y",
+                "end",
+                "riga 0 r",
+                "riga 5 g",
+                "mostra 1 0",
+                "mostra 1 5",
+                "b 0 0",
+            ],
+        );
+        assert!(errori.is_empty());
+        assert_eq!(output, vec!["r 2", "g 2", "3"]);
+        assert_eq!(piano.piastrelle.len(), 4);
+    }
+
+    #[test]
+    fn test_macro_errori() {
+        let mut piano = Piano::new();
+        let mut interprete = Interprete::new(Path::new("."));
+
+        let (.., errori) = interpreta(
+            &mut interprete,
+            &mut piano,
+            &[
+                "define C x",
+                "end",
+                "define m1 x",
+                "define m2",
+                "m1 1",
+                "end",
+                "m1",
+                "define ricorsiva",
+                "C 0 0 r 1",
+                "ricorsiva",
+                "end",
+                "ricorsiva",
+                "define errata",
+                "C 0 0 r 0",
+                "end",
+                "errata",
+            ],
+        );
+        assert_eq!(
+            errori,
+            vec![
+                "macro C con il nome di un comando",
+                "end senza define",
+                "define annidata in un'altra macro",
+                "macro m1 con 0 argomenti invece di 1",
+                "macro ricorsiva: macro ricorsiva ricorsiva",
+                "macro errata: intensità non valida",
+            ]
+        );
+
+        interpreta(&mut interprete, &mut piano, &["define aperta"]);
+        assert_eq!(
+            interprete.termina(),
+            Err(String::from("macro aperta senza end"))
+        );
+        assert_eq!(interprete.termina(), Ok(()));
+    }
+
+    #[test]
+    fn test_macro_termina() {
+        let mut piano = Piano::new();
+        let mut interprete = Interprete::new(Path::new("."));
+
+        let (.., errori) = interpreta(
+            &mut interprete,
+            &mut piano,
+            &["define fine", "C 0 0 r 1", "q", "C 1 1 r 1", "end"],
+        );
+        assert!(errori.is_empty());
+        assert_eq!(
+            interprete.esegui(&mut piano, "fine", &mut |_| ()),
+            Ok(Esito::Termina)
+        );
+        assert_eq!(piano.piastrelle.len(), 1);
+    }
+
+    #[test]
+    fn test_include() {
+        let cartella = cartella_temporanea(
+            "include",
+            &[
+                ("principale.txt", "include sotto/regole.txt\nC 0 0 r 1\n"),
+                ("sotto/regole.txt", "r g 1 r\ninclude colori.txt\n"),
+                ("sotto/colori.txt", "C 1 1 b 1\n"),
+            ],
+        );
+        let mut piano = Piano::new();
+        let mut interprete = Interprete::new(&cartella);
+
+        let (output, errori) = interpreta(
+            &mut interprete,
+            &mut piano,
+            &["include principale.txt", "s", "b 0 0"],
+        );
+        assert!(errori.is_empty());
+        assert_eq!(output, vec!["(\ng: 1 r\n)", "2"]);
+    }
+
+    #[test]
+    fn test_include_ciclico() {
+        let cartella = cartella_temporanea(
+            "include_ciclico",
+            &[
+                ("a.txt", "C 0 0 r 1\ninclude b.txt\n"),
+                ("b.txt", "\ninclude a.txt\n"),
+            ],
+        );
+        let mut piano = Piano::new();
+        let mut interprete = Interprete::new(&cartella);
+
+        let (.., errori) = interpreta(
+            &mut interprete,
+            &mut piano,
+            &["include a.txt", "include mancante.txt", "include"],
+        );
+        assert_eq!(
+            errori,
+            vec![
+                "a.txt:2: b.txt:2: inclusione ciclica di a.txt",
+                "errore aprendo il file mancante.txt",
+                "input non valido",
+            ]
+        );
+    }
+}
+
+/// Test per le funzioni `colora_rettangolo`, `spegni_rettangolo`, `colora_linea`,
+/// `colora_cerchio`, `riempi`
+mod forme {
+    #[cfg(test)]
+    use crate::*;
+
+    /// Piano con le piastrelle `coordinate` colorate una ad una con `colora`
+    fn piano_colorato(coordinate: &[(i32, i32)], colore: &str, intensita: u32) -> Piano {
+        let mut piano = Piano::new();
+        for (x, y) in coordinate {
+            piano.colora(*x, *y, String::from(colore), intensita);
+        }
+        piano
+    }
+
+    #[test]
+    fn test_colora_rettangolo() {
+        let mut piano = Piano::new();
+        piano.colora_rettangolo(2, 1, -1, 3, String::from("r"), 2);
+
+        let mut coordinate = Vec::new();
+        for x in -1..=2 {
+            for y in 1..=3 {
+                coordinate.push((x, y));
+            }
+        }
+        assert_eq!(
+            piano.colorazioni(),
+            piano_colorato(&coordinate, "r", 2).colorazioni()
+        );
+        assert_eq!(piano.blocco(0, 2), 24);
+
+        let mut punto = Piano::new();
+        punto.colora_rettangolo(5, 5, 5, 5, String::from("r"), 1);
+        assert_eq!(
+            punto.colorazioni(),
+            piano_colorato(&[(5, 5)], "r", 1).colorazioni()
+        );
+    }
+
+    #[test]
+    fn test_spegni_rettangolo() {
+        let mut piano = Piano::new();
+        piano.colora_rettangolo(0, 0, 3, 3, String::from("r"), 1);
+
+        // rettangolo più piccolo del piano
+        piano.spegni_rettangolo(1, 1, 2, 2);
+        let mut atteso = Piano::new();
+        atteso.colora_rettangolo(0, 0, 3, 3, String::from("r"), 1);
+        for (x, y) in [(1, 1), (1, 2), (2, 1), (2, 2)] {
+            atteso.spegni(x, y);
+        }
+        assert_eq!(piano.colorazioni(), atteso.colorazioni());
+
+        // rettangolo più grande del piano
+        piano.spegni_rettangolo(-1000, 3, 1000, -1000);
+        for x in 0..=3 {
+            atteso.spegni(x, 0);
+            atteso.spegni(x, 3);
+        }
+        for y in 0..=3 {
+            atteso.spegni(0, y);
+            atteso.spegni(3, y);
+        }
+        assert_eq!(piano.colorazioni(), atteso.colorazioni());
+        assert_eq!(piano.piastrelle.len(), 0);
+    }
+
+    #[test]
+    fn test_colora_linea() {
+        let linea = |(x1, y1), (x2, y2)| {
+            let mut piano = Piano::new();
+            piano.colora_linea(x1, y1, x2, y2, String::from("g"), 3);
+            piano.colorazioni()
+        };
+
+        assert_eq!(
+            linea((0, 0), (4, 0)),
+            piano_colorato(&[(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)], "g", 3).colorazioni()
+        );
+        assert_eq!(
+            linea((1, 3), (1, 0)),
+            piano_colorato(&[(1, 3), (1, 2), (1, 1), (1, 0)], "g", 3).colorazioni()
+        );
+        assert_eq!(
+            linea((0, 0), (-3, -3)),
+            piano_colorato(&[(0, 0), (-1, -1), (-2, -2), (-3, -3)], "g", 3).colorazioni()
+        );
+        assert_eq!(
+            linea((0, 0), (4, 2)),
+            piano_colorato(&[(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)], "g", 3).colorazioni()
+        );
+        assert_eq!(linea((4, 2), (0, 0)).len(), 5);
+
+        // linee agli estremi del piano
+        assert_eq!(
+            linea((i32::MIN, i32::MAX), (i32::MIN, i32::MAX - 2)),
+            piano_colorato(
+                &[
+                    (i32::MIN, i32::MAX),
+                    (i32::MIN, i32::MAX - 1),
+                    (i32::MIN, i32::MAX - 2)
+                ],
+                "g",
+                3
+            )
+            .colorazioni()
+        );
+        assert_eq!(
+            linea((i32::MAX, i32::MIN), (i32::MAX - 1, i32::MIN + 1)).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_colora_cerchio() {
+        let mut piano = Piano::new();
+        piano.colora_cerchio(0, 0, 0, String::from("b"), 1);
+        assert_eq!(
+            piano.colorazioni(),
+            piano_colorato(&[(0, 0)], "b", 1).colorazioni()
+        );
+
+        let mut piano = Piano::new();
+        piano.colora_cerchio(10, 10, 2, String::from("b"), 1);
+        let coordinate: Vec<(i32, i32)> = [
+            (2, 0),
+            (2, 1),
+            (1, 2),
+            (0, 2),
+            (-1, 2),
+            (-2, 1),
+            (-2, 0),
+            (-2, -1),
+            (-1, -2),
+            (0, -2),
+            (1, -2),
+            (2, -1),
+        ]
+        .iter()
+        .map(|(dx, dy)| (10 + dx, 10 + dy))
+        .collect();
+        assert_eq!(
+            piano.colorazioni(),
+            piano_colorato(&coordinate, "b", 1).colorazioni()
+        );
+
+        // la circonferenza è un blocco chiuso che non contiene il centro
+        assert_eq!(piano.blocco(12, 10), 12);
+        assert!(piano.stato(10, 10).is_none());
+
+        // i punti fuori dal piano sono ignorati
+        let mut piano = Piano::new();
+        piano.colora_cerchio(i32::MAX, i32::MAX, 1, String::from("b"), 1);
+        assert_eq!(
+            piano.colorazioni(),
+            piano_colorato(
+                &[(i32::MAX - 1, i32::MAX), (i32::MAX, i32::MAX - 1)],
+                "b",
+                1
+            )
+            .colorazioni()
+        );
+    }
+
+    #[test]
+    fn test_riempi() {
+        let mut piano = Piano::new();
+        piano.colora_rettangolo(0, 0, 2, 2, String::from("r"), 1);
+        piano.colora(1, 1, String::from("g"), 5);
+        piano.colora(5, 5, String::from("r"), 1);
+
+        piano.riempi(0, 0, String::from("b"), 2);
+
+        let mut atteso = Piano::new();
+        for x in 0..=2 {
+            for y in 0..=2 {
+                atteso.colora(x, y, String::from("b"), 2);
+            }
+        }
+        atteso.colora(1, 1, String::from("g"), 5);
+        atteso.colora(5, 5, String::from("r"), 1);
+        assert_eq!(piano.colorazioni(), atteso.colorazioni());
+
+        // piastrella spenta
+        piano.riempi(10, 10, String::from("b"), 2);
+        assert_eq!(piano.colorazioni(), atteso.colorazioni());
+    }
+
+    #[test]
+    fn test_forme_esegui() {
+        let mut piano = Piano::new();
+        for riga in [
+            "F 0 0 2 2 r 1",
+            "l 0 3 3 3 g 2",
+            "c 10 10 1 b 1",
+            "riempi 10 11 v 4",
+            "X 0 0 0 3",
+        ] {
+            assert_eq!(
+                esegui(&mut piano, riga, Formato::Testo, &mut |_| ()),
+                Ok(Esito::Continua)
+            );
+        }
+        assert_eq!(piano.blocco(1, 1), 6 + 6);
+        assert_eq!(piano.blocco_omogeneo(10, 11), 4 * 4);
+        assert!(piano.stato(0, 3).is_none());
+
+        assert!(esegui(&mut piano, "F 0 0 2 2 r 0", Formato::Testo, &mut |_| ()).is_err());
+        assert!(esegui(&mut piano, "c 0 0 -1 r 1", Formato::Testo, &mut |_| ()).is_err());
+        assert!(esegui(
+            &mut piano,
+            "c 0 0 3000000000 r 1",
+            Formato::Testo,
+            &mut |_| ()
+        )
+        .is_err());
+    }
+}
+
+/// Test per le funzioni `importa_griglia`, `importa_ppm`
+mod importa {
+    #[cfg(test)]
+    use crate::*;
+
+    #[test]
+    fn test_importa_griglia() {
+        let mut piano = Piano::new();
+        piano
+            .importa_griglia("r rosso 1\ng verde 3\n\nrr.g\nr  g\n", 10, -5)
+            .unwrap();
+
+        let mut atteso = Piano::new();
+        atteso.colora(10, -4, String::from("rosso"), 1);
+        atteso.colora(11, -4, String::from("rosso"), 1);
+        atteso.colora(13, -4, String::from("verde"), 3);
+        atteso.colora(10, -5, String::from("rosso"), 1);
+        atteso.colora(13, -5, String::from("verde"), 3);
+
+        assert_eq!(piano.colorazioni(), atteso.colorazioni());
+    }
+
+    #[test]
+    fn test_importa_griglia_sovrascrive() {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("blu"), 7);
+        piano.colora(1, 0, String::from("blu"), 7);
+        piano.regola(String::from("x 1 blu"));
+
+        piano.importa_griglia("a a 2\r\n\r\n.a\r\n", 0, 0).unwrap();
+
+        let mut atteso = Piano::new();
+        atteso.colora(0, 0, String::from("blu"), 7);
+        atteso.colora(1, 0, String::from("a"), 2);
+        assert_eq!(piano.colorazioni(), atteso.colorazioni());
+        assert_eq!(piano.regole.len(), 1);
+    }
+
+    #[test]
+    fn test_importa_griglia_invalida() {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("blu"), 7);
+
+        for (griglia, errore) in [
+            ("r rosso\n\nr\n", "legenda invalida (r rosso)"),
+            ("r rosso 0\n\nr\n", "legenda invalida (intensità 0)"),
+            ("rr rosso 1\n\nr\n", "legenda invalida (rr rosso 1)"),
+            (
+                "r rosso 1\nr verde 1\n\nr\n",
+                "legenda invalida (carattere r)",
+            ),
+            ("r rosso 1\n\nrrx\n", "carattere x non in legenda"),
+        ] {
+            assert_eq!(
+                piano.importa_griglia(griglia, 0, 0),
+                Err(String::from(errore))
+            );
+        }
+
+        let mut atteso = Piano::new();
+        atteso.colora(0, 0, String::from("blu"), 7);
+        assert_eq!(piano.colorazioni(), atteso.colorazioni());
+    }
+
+    #[test]
+    fn test_importa_ppm() {
+        let p3 =
+            b"P3\n# commento\n3 2\n255\n255 0 0  0 0 0  0 255 0\n0 0 0  16 32 48  255 255 255\n";
+
+        let mut piano = Piano::new();
+        piano.importa_ppm(p3, 1, 1, 4).unwrap();
+
+        let mut atteso = Piano::new();
+        atteso.colora(1, 2, String::from("ff0000"), 4);
+        atteso.colora(3, 2, String::from("00ff00"), 4);
+        atteso.colora(2, 1, String::from("102030"), 4);
+        atteso.colora(3, 1, String::from("ffffff"), 4);
+        assert_eq!(piano.colorazioni(), atteso.colorazioni());
+
+        // la stessa immagine in formato binario
+        let mut p6 = b"P6 3 2 255\n".to_vec();
+        p6.extend([
+            255, 0, 0, 0, 0, 0, 0, 255, 0, 0, 0, 0, 16, 32, 48, 255, 255, 255,
+        ]);
+        let mut piano = Piano::new();
+        piano.importa_ppm(&p6, 1, 1, 4).unwrap();
+        assert_eq!(piano.colorazioni(), atteso.colorazioni());
+
+        // valore massimo diverso da 255
+        let mut piano = Piano::new();
+        piano.importa_ppm(b"P3 1 1 15 15 0 5", 0, 0, 1).unwrap();
+        assert_eq!(piano.stato(0, 0).unwrap().colore, "ff0055");
+    }
+
+    #[test]
+    fn test_importa_ppm_invalida() {
+        let mut piano = Piano::new();
+
+        for (immagine, errore) in [
+            (
+                &b"P3 2 1"[..],
+                "immagine invalida (intestazione incompleta)",
+            ),
+            (b"P5 1 1 255 0", "immagine invalida (formato P5)"),
+            (b"P3 x 1 255 0 0 0", "immagine invalida (dimensione x)"),
+            (b"P3 1 1 0 0 0 0", "immagine invalida (valore massimo 0)"),
+            (b"P3 2 1 255 0 0 0", "immagine invalida (pixel mancanti)"),
+            (
+                b"P3 1 1 9 10 0 0",
+                "immagine invalida (campione oltre il valore massimo)",
+            ),
+        ] {
+            assert_eq!(
+                piano.importa_ppm(immagine, 0, 0, 1),
+                Err(String::from(errore))
+            );
+        }
+        assert_eq!(
+            piano.importa_ppm(b"P3 1 1 255 1 1 1", 0, 0, 0),
+            Err(String::from("intensità non valida"))
+        );
+        assert_eq!(piano.piastrelle.len(), 0);
+    }
+
+    #[test]
+    fn test_importa_ppm_dimensione_zero() {
+        let mut piano = Piano::new();
+        for immagine in [&b"P3 0 1 255"[..], b"P3 1 0 255", b"P6 0 0 255"] {
+            assert_eq!(
+                piano.importa_ppm(immagine, 0, 0, 1),
+                Err(String::from("immagine invalida (dimensione 0)"))
+            );
+        }
+        assert_eq!(piano.piastrelle.len(), 0);
+    }
+
+    #[test]
+    fn test_importa_ppm_dimensione_troppo_grande() {
+        let mut piano = Piano::new();
+        let enorme = format!("P3 {} {} 255 0 0 0", usize::MAX / 2, 2);
+        let grande = format!("P3 {} {} 255 0 0 0", usize::MAX / 3 + 1, 1);
+        for immagine in [enorme, grande] {
+            assert_eq!(
+                piano.importa_ppm(immagine.as_bytes(), 0, 0, 1),
+                Err(String::from("immagine invalida (dimensione troppo grande)"))
+            );
+        }
+        assert_eq!(piano.piastrelle.len(), 0);
+    }
+
+    #[test]
+    fn test_importa_fuori_dal_piano() {
+        let mut piano = Piano::new();
+        let fuori = Err(String::from("piastrella fuori dal piano"));
+
+        // la prima piastrella è nel piano, la seconda no
+        assert_eq!(piano.importa_griglia("r r 1\n\nrr\n", i32::MAX, 0), fuori);
+        assert_eq!(piano.importa_griglia("r r 1\n\nr\nr\n", 0, i32::MAX), fuori);
+        assert_eq!(
+            piano.importa_ppm(b"P3 2 1 255 1 1 1 1 1 1", i32::MAX, 0, 1),
+            fuori
+        );
+        assert_eq!(
+            piano.importa_ppm(b"P3 1 2 255 1 1 1 1 1 1", 0, i32::MAX, 1),
+            fuori
+        );
+        assert_eq!(piano.piastrelle.len(), 0);
+
+        // all'estremo del piano
+        piano
+            .importa_griglia("r r 1\n\nr\n", i32::MAX, i32::MAX)
+            .unwrap();
+        piano
+            .importa_ppm(b"P3 1 1 255 1 1 1", i32::MIN, i32::MAX, 1)
+            .unwrap();
+        assert_eq!(piano.piastrelle.len(), 2);
+    }
+
+    #[test]
+    fn test_importa_esegui() {
+        let cartella = std::env::temp_dir().join("piastrelle_importa");
+        std::fs::create_dir_all(&cartella).unwrap();
+        let griglia = cartella.join("griglia.txt");
+        let immagine = cartella.join("immagine.ppm");
+        std::fs::write(&griglia, "r rosso 2\n\nr\nrr\n").unwrap();
+        std::fs::write(&immagine, "P3 1 1 255 0 0 255").unwrap();
+
+        let mut piano = Piano::new();
+        for riga in [
+            format!("I {} 0 0", griglia.display()),
+            format!("importa {} 5 5 3", immagine.display()),
+        ] {
+            assert_eq!(
+                esegui(&mut piano, &riga, Formato::Testo, &mut |_| ()),
+                Ok(Esito::Continua)
+            );
+        }
+        assert_eq!(piano.blocco(0, 0), 6);
+        assert_eq!(
+            piano.stato(5, 5),
+            Some(Colorazione {
+                colore: String::from("0000ff"),
+                intensita: 3
+            })
+        );
+
+        let riga = format!("I {} 0 0 3", griglia.display());
+        assert!(esegui(&mut piano, &riga, Formato::Testo, &mut |_| ()).is_err());
+        assert!(esegui(
+            &mut piano,
+            "I mancante.txt 0 0",
+            Formato::Testo,
+            &mut |_| ()
+        )
+        .is_err());
+    }
+}
+
+mod esporta {
+    #[cfg(test)]
+    use crate::*;
+
+    /// Costruisce un piano eseguendo dei comandi
+    fn piano_da_comandi(righe: &[&str]) -> Piano {
+        let mut piano = Piano::new();
+        for riga in righe {
+            esegui(&mut piano, riga, Formato::Testo, &mut |_| ()).unwrap();
+        }
+        piano
+    }
+
+    #[test]
+    fn test_esporta_csv() {
+        let piano = piano_da_comandi(&["C 1 0 verde 3", "C 0 5 rosso 1", "C -2 1 a,\"b 2"]);
+        assert_eq!(
+            piano.esporta_csv(),
+            "x,y,colore,intensita\n-2,1,\"a,\"\"b\",2\n0,5,rosso,1\n1,0,verde,3\n"
+        );
+        assert_eq!(Piano::new().esporta_csv(), "x,y,colore,intensita\n");
+    }
+
+    #[test]
+    fn test_importa_csv() {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("blu"), 7);
+        piano
+            .importa_csv("0,0,rosso,2\r\n\r\n\"-1\",3,\"a \"\"b\"\"\",1\n 4 ,5,v,9")
+            .unwrap_err();
+        piano
+            .importa_csv("0,0,rosso,2\r\n\r\n-1,3,\"a,\"\"b\"\"\",1\n 4 ,5,v,9")
+            .unwrap();
+
+        let mut atteso = Piano::new();
+        atteso.colora(0, 0, String::from("rosso"), 2);
+        atteso.colora(-1, 3, String::from("a,\"b\""), 1);
+        atteso.colora(4, 5, String::from("v"), 9);
+        assert_eq!(piano.colorazioni(), atteso.colorazioni());
+    }
+
+    #[test]
+    fn test_importa_csv_invalido() {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("blu"), 7);
+
+        for (csv, errore) in [
+            ("x,y,colore,intensita\n0,0,r\n", "csv invalido (riga 2)"),
+            ("0,0,r,1,2\n", "csv invalido (riga 1)"),
+            ("0,0,r,1\nx,0,r,1\n", "csv invalido (riga 2)"),
+            ("0,0,r,0\n", "csv invalido (riga 1)"),
+            ("0,0,,1\n", "csv invalido (riga 1)"),
+            ("0,0,\"r\"x,1\n", "csv invalido (riga 1)"),
+            ("0,0,\"r,1\n", "csv invalido (riga 1)"),
+            ("1,1,r,1\nx,y,colore,intensita\n", "csv invalido (riga 2)"),
+        ] {
+            assert_eq!(piano.importa_csv(csv), Err(String::from(errore)));
+        }
+
+        let mut atteso = Piano::new();
+        atteso.colora(0, 0, String::from("blu"), 7);
+        assert_eq!(piano.colorazioni(), atteso.colorazioni());
+    }
+
+    #[test]
+    fn test_esporta_regole() {
+        let mut piano = piano_da_comandi(&[
+            "C 0 0 r 1",
+            "C 1 0 r 1",
+            "r g 2 r",
+            "r b 1 r 1 \"v",
+            "p 0 1",
+        ]);
+        assert_eq!(
+            piano.esporta_regole(),
+            concat!(
+                r#"[{"colore":"g","requisiti":[{"coefficiente":2,"colore":"r"}],"utilizzo":1},"#,
+                r#"{"colore":"b","requisiti":[{"coefficiente":1,"colore":"r"},{"coefficiente":1,"colore":"\"v"}],"utilizzo":0}]"#
+            )
+        );
+
+        piano.regole.clear();
+        assert_eq!(piano.esporta_regole(), "[]");
+    }
+
+    #[test]
+    fn test_importa_regole() {
+        let mut piano = piano_da_comandi(&["r x 1 y"]);
+        piano
+            .importa_regole(
+                r#" [ {"utilizzo": 4, "colore": "g", "requisiti": [{"colore": "r", "coefficiente": 2}]},
+                      {"colore": "b", "requisiti": [{"coefficiente": 1, "colore": "a\\b"}], "extra": [null, true]} ] "#,
+            )
+            .unwrap();
+
+        let atteso = piano_da_comandi(&["r x 1 y", "r g 2 r", "r b 1 a\\b"]);
+        assert_eq!(piano.stampa(), atteso.stampa());
+        assert_eq!(
+            piano.regole.iter().map(|r| r.utilizzo).collect::<Vec<_>>(),
+            vec![0, 4, 0]
+        );
+    }
+
+    #[test]
+    fn test_importa_regole_invalide() {
+        let mut piano = piano_da_comandi(&["r x 1 y"]);
+
+        for (json, errore) in [
+            ("[", "json invalido (carattere 1)"),
+            ("[] []", "json invalido (carattere 3)"),
+            (r#"{"colore":"g"}"#, "regole invalide (array atteso)"),
+            (
+                r#"[{"colore":"g"}]"#,
+                "regole invalide (regola 0: requisiti mancanti)",
+            ),
+            (
+                r#"[{"colore":"g b","requisiti":[]}]"#,
+                "regole invalide (regola 0: colore invalido)",
+            ),
+            (
+                r#"[{"colore":"g","requisiti":[{"coefficiente":"1","colore":"r"}]}]"#,
+                "regole invalide (regola 0: coefficiente invalido)",
+            ),
+            (
+                r#"[{"colore":"g","requisiti":[{"coefficiente":1,"colore":"r"}]},
+                   {"colore":"g","requisiti":[{"coefficiente":9,"colore":"r"}]}]"#,
+                "regole invalide (regola 1: regola invalida (somma coefficienti 9 maggiore di 8))",
+            ),
+            (
+                r#"[{"colore":"g","requisiti":[]}]"#,
+                "regole invalide (regola 0: regola invalida (mancanza coefficienti))",
+            ),
+            (
+                r#"[{"colore":"g","requisiti":[{"coefficiente":1,"colore":"r"}],"utilizzo":-1}]"#,
+                "regole invalide (regola 0: utilizzo invalido)",
+            ),
+        ] {
+            assert_eq!(piano.importa_regole(json), Err(String::from(errore)));
+        }
+        assert_eq!(piano.stampa(), "(\nx: 1 y\n)");
+    }
+
+    #[test]
+    fn test_andata_ritorno() {
+        let mut piano = piano_da_comandi(&[
+            "F 0 0 3 2 r 2",
+            "l -3 -3 3 3 g 1",
+            "C 5 5 \"virgolette\" 4",
+            "C 6 5 a,b 1",
+            "r g 3 r",
+            "r b 1 g 2 r",
+            "r r 1 \"virgolette\"",
+            "P 0 0",
+            "p 6 5",
+        ]);
+
+        let mut copia = Piano::new();
+        copia.importa_csv(&piano.esporta_csv()).unwrap();
+        copia.importa_regole(&piano.esporta_regole()).unwrap();
+        assert_eq!(copia.colorazioni(), piano.colorazioni());
+        assert_eq!(copia.esporta_regole(), piano.esporta_regole());
+
+        // anche attraverso i comandi e dei file
+        let cartella = std::env::temp_dir().join("piastrelle_esporta");
+        std::fs::create_dir_all(&cartella).unwrap();
+        let csv = cartella.join("piastrelle.csv");
+        let json = cartella.join("regole.json");
+        for riga in [
+            format!("E piastrelle {}", csv.display()),
+            format!("esporta regole {}", json.display()),
+        ] {
+            esegui(&mut piano, &riga, Formato::Testo, &mut |_| ()).unwrap();
+        }
+        let mut copia = Piano::new();
+        for riga in [
+            format!("K piastrelle {}", csv.display()),
+            format!("carica regole {}", json.display()),
+        ] {
+            esegui(&mut copia, &riga, Formato::Testo, &mut |_| ()).unwrap();
+        }
+        assert_eq!(copia.colorazioni(), piano.colorazioni());
+        assert_eq!(copia.esporta_regole(), piano.esporta_regole());
+    }
+
+    #[test]
+    fn test_esporta_esegui() {
+        let mut piano = piano_da_comandi(&["C 0 0 r 1", "r g 1 r"]);
+
+        let mut output = Vec::new();
+        for riga in ["E piastrelle", "E regole"] {
+            esegui(&mut piano, riga, Formato::Testo, &mut |s| output.push(s)).unwrap();
+        }
+        esegui(&mut piano, "E regole", Formato::Json, &mut |s| {
+            output.push(s)
+        })
+        .unwrap();
+        assert_eq!(
+            output,
+            vec![
+                "x,y,colore,intensita\n0,0,r,1",
+                r#"[{"colore":"g","requisiti":[{"coefficiente":1,"colore":"r"}],"utilizzo":0}]"#,
+                r#"{"comando":"E","argomenti":["regole"],"risultato":[{"colore":"g","requisiti":[{"coefficiente":1,"colore":"r"}],"utilizzo":0}]}"#,
+            ]
+        );
+
+        for riga in ["E", "E tutto", "K piastrelle", "K regole mancante.json"] {
+            assert!(esegui(&mut piano, riga, Formato::Testo, &mut |_| ()).is_err());
+        }
+    }
+}
+
+mod generatore {
+    #[cfg(test)]
+    use crate::generatore::*;
+    #[cfg(test)]
+    use crate::*;
+
+    #[test]
+    fn test_casuale() {
+        let sequenza = |seme| {
+            let mut casuale = Casuale::new(seme);
+            (0..100).map(|_| casuale.prossimo()).collect::<Vec<_>>()
+        };
+        assert_eq!(sequenza(42), sequenza(42));
+        assert_ne!(sequenza(42), sequenza(43));
+
+        let mut casuale = Casuale::new(0);
+        let estratti: Vec<i64> = (0..1000).map(|_| casuale.intervallo(-2, 2)).collect();
+        assert!(estratti.iter().all(|n| (-2..=2).contains(n)));
+        assert!((-2..=2).all(|n| estratti.contains(&n)));
+        assert_eq!(casuale.intervallo(7, 7), 7);
+        assert_eq!(*casuale.scegli(&["a"]), "a");
+    }
+
+    #[test]
+    fn test_profili() {
+        for (nome, profilo) in PROFILI {
+            assert_eq!(Profilo::cerca(nome), Some(profilo));
+
+            let comandi = genera(profilo, 1, 1);
+            assert_eq!(comandi, genera(profilo, 1, 1));
+            assert_ne!(comandi, genera(profilo, 2, 1));
+            assert!(comandi.ends_with("\nq\n"));
+
+            // ogni comando generato è valido e produce dell'output
+            let mut piano = Piano::new();
+            let mut output = 0;
+            for riga in comandi.lines() {
+                let esito = esegui(&mut piano, riga, Formato::Testo, &mut |_| output += 1);
+                assert!(esito.is_ok(), "{nome}: {riga}");
+            }
+            assert!(output > 0, "{nome}");
+        }
+        assert_eq!(Profilo::cerca("boh"), None);
+    }
+
+    #[test]
+    fn test_scala() {
+        let comandi = genera(Profilo::Denso, 5, 1);
+        let scalati = genera(Profilo::Denso, 5, 3);
+        assert!(scalati.lines().count() > 4 * comandi.lines().count());
+        assert!(scalati.contains("? 60 60\n"));
+        assert!(!comandi.contains("? 21 "));
+    }
+
+    #[test]
+    #[should_panic(expected = "scala non valida")]
+    fn test_scala_troppo_grande() {
+        genera(Profilo::Denso, 0, Profilo::Denso.scala_massima() + 1);
+    }
+}
+
+mod minimizzatore {
+    #[cfg(test)]
+    use crate::minimizzatore::*;
+    #[cfg(test)]
+    use std::path::PathBuf;
+
+    fn cartella(nome: &str) -> PathBuf {
+        let cartella = std::env::temp_dir().join(format!("piastrelle_minimizza_{nome}"));
+        std::fs::create_dir_all(&cartella).unwrap();
+        cartella
+    }
+
+    fn righe(testo: &str) -> Vec<(usize, String)> {
+        testo.lines().map(String::from).enumerate().collect()
+    }
+
+    fn comandi(righe: &[(usize, String)]) -> Vec<&str> {
+        righe.iter().map(|(_, riga)| riga.as_str()).collect()
+    }
+
+    #[test]
+    fn test_minimizza() {
+        let minimo = minimizza((0..100).collect(), |candidato: &[u32]| {
+            candidato.contains(&13) && candidato.contains(&71) && candidato.contains(&72)
+        });
+        assert_eq!(minimo, vec![13, 71, 72]);
+
+        // l'ordine relativo è mantenuto
+        let minimo = minimizza(vec!['c', 'a', 'b', 'a'], |candidato| {
+            candidato.windows(2).any(|coppia| coppia == ['a', 'b'])
+        });
+        assert_eq!(minimo, vec!['a', 'b']);
+
+        assert_eq!(minimizza(vec![1], |_| true), vec![1]);
+    }
+
+    #[test]
+    fn test_minimizza_panico() {
+        let cartella = cartella("panico");
+        let input = righe("C 0 0 r 1\nr z 1 r\n? 0 0\nr z 9 g\np 1 1\nq\n");
+        assert!(Criterio::Panico.fallisce(&input, &cartella));
+
+        let minimo = minimizza(input, |candidato| {
+            Criterio::Panico.fallisce(candidato, &cartella)
+        });
+        assert_eq!(comandi(&minimo), vec!["r z 9 g"]);
+        assert!(!Criterio::Panico.fallisce(&righe("C 0 0 r 1\n"), &cartella));
+    }
+
+    #[test]
+    fn test_minimizza_risposta() {
+        let cartella = cartella("risposta");
+        let input: Vec<String> = ["C 0 0 r 1", "C 5 5 g 1", "C 1 1 r 2", "? 5 5", "b 0 0", "q"]
+            .map(String::from)
+            .to_vec();
+
+        // l'output corretto non ha righe sbagliate
+        assert!(Criterio::risposta(&input, "g 1\n3\n", &cartella).is_err());
+
+        // `b 0 0` produce 3 invece di 4
+        let criterio = Criterio::risposta(&input, "g 1\n4\n", &cartella).unwrap();
+        let input: Vec<(usize, String)> = input.into_iter().enumerate().collect();
+        assert!(criterio.fallisce(&input, &cartella));
+        let minimo = minimizza(input, |candidato| criterio.fallisce(candidato, &cartella));
+        assert_eq!(comandi(&minimo), vec!["C 0 0 r 1", "C 1 1 r 2", "b 0 0"]);
+    }
+
+    #[test]
+    fn test_minimizza_risposta_mancante() {
+        let cartella = cartella("risposta_mancante");
+        let minimo = |input: &[&str], atteso: &str| {
+            let input: Vec<String> = input.iter().map(|riga| String::from(*riga)).collect();
+            let criterio = Criterio::risposta(&input, atteso, &cartella).unwrap();
+            let input: Vec<(usize, String)> = input.into_iter().enumerate().collect();
+            assert!(criterio.fallisce(&input, &cartella));
+            let minimo = minimizza(input, |candidato| criterio.fallisce(candidato, &cartella));
+            comandi(&minimo).join("/")
+        };
+        let input = ["C 0 0 a 1", "C 1 1 b 2", "? 0 0", "? 5 5", "? 1 1", "q"];
+
+        // `? 5 5` non produce la riga attesa, `? 1 1` risponde correttamente
+        assert_eq!(minimo(&input, "a 1\nnull\nb 2\n"), "? 5 5");
+        // con le righe allineate la colpa resta della riga sbagliata
+        assert_eq!(minimo(&input, "a 1\nb 3\n"), "C 1 1 b 2/? 1 1");
+        // la riga mancante è l'ultima, `q` non risponde mai
+        assert_eq!(
+            minimo(&["C 0 0 a 1", "? 0 0", "? 5 5", "q"], "a 1\nnull\n"),
+            "? 5 5"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_minimizza_crash() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // il programma termina per un segnale se l'input contiene `boom`
+        let cartella = cartella("crash");
+        let programma = cartella.join("programma.sh");
+        std::fs::write(
+            &programma,
+            "#!/bin/sh\ngrep -q boom && kill -SEGV $$\nexit 0\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&programma, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let criterio = Criterio::Crash(programma);
+        let minimo = minimizza(righe("a\nb\nboom\nc\n"), |candidato| {
+            criterio.fallisce(candidato, &cartella)
+        });
+        assert_eq!(comandi(&minimo), vec!["boom"]);
+    }
+}
+
+/// Test per la tavolozza dei colori del piano
+mod tavolozza {
+    #[cfg(test)]
+    use crate::*;
+
+    #[test]
+    fn test_interna() {
+        let mut tavolozza = Tavolozza::default();
+
+        let rosso = tavolozza.interna("rosso");
+        let verde = tavolozza.interna("verde");
+        assert_ne!(rosso, verde);
+        assert_eq!(tavolozza.interna("rosso"), rosso);
+        assert_eq!(tavolozza.cerca("verde"), Some(verde));
+        assert_eq!(tavolozza.cerca("blu"), None);
+        assert_eq!(tavolozza.nome(rosso), "rosso");
+        assert_eq!(tavolozza.nome(verde), "verde");
+    }
+
+    #[test]
+    fn test_piano() {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("r"), 1);
+        piano.colora(1, 0, String::from("b"), 2);
+        piano.regola(String::from("z 1 r 1 g"));
+        piano.colora(0, 0, String::from("z"), 3);
+        piano.spegni(1, 0);
+
+        // i colori restano nella tavolozza anche quando non sono più usati
+        let z = piano
+            .piastrelle
+            .get(&Piastrella { x: 0, y: 0 })
+            .unwrap()
+            .colore;
+        assert_eq!(piano.tavolozza.nome(z), "z");
+        assert!(piano.tavolozza.cerca("b").is_some());
+        assert_eq!(piano.colori(), vec!["g", "r", "z"]);
+        assert_eq!(
+            piano.colorazioni(),
+            HashMap::from([(
+                Piastrella { x: 0, y: 0 },
+                Colorazione {
+                    colore: String::from("z"),
+                    intensita: 3
+                }
+            )])
+        );
+
+        // le regole invalide, o solo validate, non aggiungono colori
+        assert!(piano.valida_regola("w 9 q").is_err());
+        assert!(piano.valida_regola("w 1 q").is_ok());
+        assert_eq!(piano.tavolozza.cerca("q"), None);
+        assert_eq!(piano.tavolozza.cerca("w"), None);
+        for riga in ["r w 9 q", "R w 1 q", "M 7 w 1 q", "M 0 w 9 q"] {
+            let _ = esegui(&mut piano, riga, Formato::Testo, &mut |_| ());
+        }
+        assert!(piano
+            .importa_regole(
+                r#"[{"colore":"w","requisiti":[{"coefficiente":1,"colore":"q"}]},{"colore":"x"}]"#
+            )
+            .is_err());
+        assert_eq!(piano.tavolozza.cerca("q"), None);
+        assert_eq!(piano.colori(), vec!["g", "r", "z"]);
+    }
+}
+
+/// Test per gli archivi delle piastrelle, `Blocchi` e `HashMap`
+mod archivio {
+    #[cfg(test)]
+    use crate::*;
+
+    use crate::generatore::Casuale;
+
+    fn tinta(intensita: u32) -> Tinta {
+        let mut tavolozza = Tavolozza::default();
+        Tinta {
+            colore: tavolozza.interna("r"),
+            intensita,
+        }
+    }
+
+    #[test]
+    fn test_blocchi() {
+        let mut blocchi = Blocchi::default();
+        let estremi = [
+            (0, 0),
+            (-1, -1),
+            (63, 63),
+            (64, 0),
+            (-64, -65),
+            (i32::MIN, i32::MAX),
+            (i32::MAX, i32::MIN),
+        ];
+        for (i, (x, y)) in estremi.into_iter().enumerate() {
+            assert_eq!(
+                blocchi.insert(Piastrella { x, y }, tinta(i as u32 + 1)),
+                None
+            );
+        }
+        assert_eq!(blocchi.len(), estremi.len());
+        for (i, (x, y)) in estremi.into_iter().enumerate() {
+            assert_eq!(
+                blocchi.get(&Piastrella { x, y }),
+                Some(&tinta(i as u32 + 1))
+            );
+        }
+        assert_eq!(blocchi.get(&Piastrella { x: 1, y: 0 }), None);
+        assert_eq!(blocchi.get(&Piastrella { x: -1, y: 0 }), None);
+
+        let mut iterate: Vec<(i32, i32)> = blocchi.iter().map(|(p, _)| (p.x, p.y)).collect();
+        let mut attese = estremi.to_vec();
+        iterate.sort();
+        attese.sort();
+        assert_eq!(iterate, attese);
+
+        assert_eq!(
+            blocchi.insert(Piastrella { x: -1, y: -1 }, tinta(9)),
+            Some(tinta(2))
+        );
+        assert_eq!(blocchi.remove(&Piastrella { x: 64, y: 0 }), Some(tinta(4)));
+        assert_eq!(blocchi.remove(&Piastrella { x: 64, y: 0 }), None);
+        blocchi.retain(|Piastrella { x, .. }, _| *x >= 0);
+        assert_eq!(blocchi.len(), 3);
+    }
+
+    #[test]
+    fn test_blocchi_casuale() {
+        // operazioni casuali su pochi blocchi, che diventano densi e tornano sparsi
+        let mut blocchi = Blocchi::default();
+        let mut riferimento: HashMap<Piastrella, Tinta> = HashMap::new();
+        let mut casuale = Casuale::new(7);
+
+        for passo in 0..100_000 {
+            let piastrella = Piastrella {
+                x: casuale.intervallo(-70, 70) as i32,
+                y: casuale.intervallo(-70, 70) as i32,
+            };
+            // prima accende molte piastrelle, poi ne spegne molte
+            let accendi = casuale.intervallo(0, 9) < if passo < 20_000 { 8 } else { 1 };
+            if accendi {
+                let tinta = tinta(casuale.intervallo(1, 100) as u32);
+                assert_eq!(
+                    blocchi.insert(piastrella.clone(), tinta),
+                    riferimento.insert(piastrella, tinta)
+                );
+            } else {
+                assert_eq!(blocchi.remove(&piastrella), riferimento.remove(&piastrella));
+            }
+            assert_eq!(blocchi.len(), riferimento.len());
+        }
+
+        let copia: HashMap<Piastrella, Tinta> = blocchi.iter().map(|(p, t)| (p, *t)).collect();
+        assert_eq!(copia, riferimento);
+    }
+
+    /// Esegue le stesse operazioni su un piano con l'archivio `A`
+    fn scenario<A: Archivio>() -> (Vec<Option<u32>>, HashMap<Piastrella, Colorazione>) {
+        let mut piano: Piano<A> = Piano::con_archivio();
+        piano.colora_rettangolo(-70, -10, 70, 10, String::from("r"), 2);
+        piano.colora_linea(-70, -10, 70, 10, String::from("g"), 1);
+        piano.spegni_rettangolo(-5, -20, 5, 20);
+        piano.regola(String::from("b 2 g"));
+        piano.regola(String::from("g 3 r"));
+        piano.propaga_blocco(-70, -10);
+        piano.propaga(0, 30);
+
+        let risultati = vec![
+            Some(piano.blocco(-70, -10)),
+            Some(piano.blocco_omogeneo(70, 10)),
+            piano.lung(-70, -10, 70, 10),
+            piano.lung(-70, -10, -6, 0),
+            piano.pista(6, 0, String::from("NE NE SS")),
+        ];
+        (risultati, piano.colorazioni())
+    }
+
+    #[test]
+    fn test_archivi_equivalenti() {
+        assert_eq!(
+            scenario::<Blocchi>(),
+            scenario::<HashMap<Piastrella, Tinta>>()
+        );
+    }
+}
+
+/// Test per l'istogramma dell'intorno e l'indice delle regole
+mod indice {
+    #[cfg(test)]
+    use crate::*;
+
+    use crate::generatore::Casuale;
+    use crate::indice::{IndiceRegole, Intorno};
+
+    #[test]
+    fn test_intorno() {
+        let mut tavolozza = Tavolozza::default();
+        let (r, g, b) = (
+            tavolozza.interna("r"),
+            tavolozza.interna("g"),
+            tavolozza.interna("b"),
+        );
+
+        let mut intorno = Intorno::default();
+        for colore in [r, g, r, r, g, r, r, r] {
+            intorno.aggiungi(colore);
+        }
+        assert_eq!(intorno.conta(r), 6);
+        assert_eq!(intorno.conta(g), 2);
+        assert_eq!(intorno.conta(b), 0);
+        assert_eq!(intorno.iter().count(), 2);
+    }
+
+    #[test]
+    fn test_prima_applicabile() {
+        // regole e intorni casuali: l'indice trova la stessa regola della scansione
+        let mut piano = Piano::new();
+        let colori = ["a", "b", "c", "d", "e", "f"];
+        let mut casuale = Casuale::new(3);
+        for _ in 0..200 {
+            let mut disponibili = colori.to_vec();
+            let mut regola = String::from(*casuale.scegli(&colori));
+            for _ in 0..casuale.intervallo(1, 3) {
+                let colore = disponibili
+                    .remove(casuale.intervallo(0, disponibili.len() as i64 - 1) as usize);
+                regola.push_str(&format!(" {} {colore}", casuale.intervallo(1, 2)));
+            }
+            piano.regola(regola);
+        }
+        let indice = IndiceRegole::new(&piano.regole);
+
+        for _ in 0..2000 {
+            let mut intorno = Intorno::default();
+            for _ in 0..casuale.intervallo(0, 8) {
+                let colore = *casuale.scegli(&colori);
+                intorno.aggiungi(piano.tavolozza.interna(colore));
+            }
+            let attesa = piano
+                .regole
+                .iter()
+                .position(|regola| intorno.requisito_mancante(regola).is_none());
+            assert_eq!(indice.prima_applicabile(&piano.regole, &intorno), attesa);
+        }
+    }
+
+    #[test]
+    fn test_indice_aggiornato() {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("r"), 1);
+        piano.colora(1, 1, String::from("g"), 1);
+        piano.regola(String::from("x 1 r 1 g"));
+        piano.regola(String::from("y 1 g"));
+
+        let colore = |piano: &Piano| piano.stato(0, 1).map(|c| c.colore);
+        piano.propaga(0, 1);
+        assert_eq!(colore(&piano).as_deref(), Some("x"));
+
+        piano.ordina();
+        piano.spegni(0, 1);
+        piano.propaga(0, 1);
+        assert_eq!(colore(&piano).as_deref(), Some("y"));
+
+        piano.sostituisci_regola(0, String::from("z 1 r"));
+        piano.spegni(0, 1);
+        piano.propaga(0, 1);
+        assert_eq!(colore(&piano).as_deref(), Some("z"));
+
+        piano.rimuovi_regola(0);
+        piano.spegni(0, 1);
+        piano.propaga(0, 1);
+        assert_eq!(colore(&piano).as_deref(), Some("x"));
+
+        piano.regole.clear();
+        piano._invalida_indice();
+        piano
+            .importa_regole(r#"[{"colore":"w","requisiti":[{"coefficiente":2,"colore":"r"}]},{"colore":"v","requisiti":[{"coefficiente":1,"colore":"g"}]}]"#)
+            .unwrap();
+        piano.spegni(0, 1);
+        piano.propaga(0, 1);
+        assert_eq!(colore(&piano).as_deref(), Some("v"));
+    }
+}
+
+/// Test per le operazioni parallele: `anteprima_blocco`, `propaga_blocco`, `etichetta_blocchi`
+mod parallelo {
+    #[cfg(test)]
+    use crate::*;
+
+    use crate::generatore::Casuale;
+
+    /// Piano casuale di circa 3000 piastrelle su un quadrato di lato 80, con regole
+    fn piano_casuale(seme: u64) -> Piano {
+        let mut piano = Piano::new();
+        let mut casuale = Casuale::new(seme);
+        let colori = ["r", "g", "b"];
+        for _ in 0..4000 {
+            let x = casuale.intervallo(-40, 40) as i32;
+            let y = casuale.intervallo(-40, 40) as i32;
+            let colore = *casuale.scegli(&colori);
+            piano.colora(x, y, String::from(colore), casuale.intervallo(1, 9) as u32);
+        }
+        piano.regola(String::from("g 3 r"));
+        piano.regola(String::from("b 2 g 2 r"));
+        piano.regola(String::from("r 4 b"));
+        piano
+    }
+
+    #[test]
+    fn test_propaga_blocco_thread() {
+        let mut riferimento = piano_casuale(3);
+        riferimento.imposta_thread(1);
+        // prima piastrella del blocco più grande, diviso tra più thread
+        let etichette = riferimento.etichetta_blocchi(false);
+        let mut piastrelle = vec![0; etichette.len()];
+        etichette
+            .iter()
+            .for_each(|(.., etichetta)| piastrelle[*etichetta] += 1);
+        let massimo = (0..piastrelle.len())
+            .max_by_key(|i| piastrelle[*i])
+            .unwrap();
+        let (Piastrella { x, y }, ..) = etichette.iter().find(|(.., e)| *e == massimo).unwrap();
+        let (x, y) = (*x, *y);
+        assert!(riferimento._blocco_generico(x, y, false).1.len() > 2000);
+        let attese = riferimento.stampa_anteprima_blocco(x, y);
+        riferimento.propaga_blocco(x, y);
+
+        for thread in [2, 3, 8] {
+            let mut piano = piano_casuale(3);
+            piano.imposta_thread(thread);
+            assert_eq!(piano.stampa_anteprima_blocco(x, y), attese);
+            piano.propaga_blocco(x, y);
+            assert_eq!(piano.colorazioni(), riferimento.colorazioni());
+            assert_eq!(piano.esporta_regole(), riferimento.esporta_regole());
+        }
+    }
+
+    #[test]
+    fn test_etichetta_blocchi() {
+        let mut piano = piano_casuale(11);
+        for omogeneo in [false, true] {
+            piano.imposta_thread(1);
+            let etichette = piano.etichetta_blocchi(omogeneo);
+            assert_eq!(etichette.len(), piano.piastrelle.len());
+
+            // ogni blocco è quello calcolato a partire dalla sua prima piastrella
+            let mut blocchi: Vec<HashSet<Piastrella>> = Vec::new();
+            for (piastrella, _, etichetta) in &etichette {
+                if *etichetta == blocchi.len() {
+                    let (.., blocco) = piano._blocco_generico(piastrella.x, piastrella.y, omogeneo);
+                    blocchi.push(blocco);
+                }
+                assert!(blocchi[*etichetta].contains(piastrella));
+            }
+            let totale: usize = blocchi.iter().map(HashSet::len).sum();
+            assert_eq!(totale, etichette.len());
+
+            for thread in [2, 3, 8] {
+                piano.imposta_thread(thread);
+                assert_eq!(piano.etichetta_blocchi(omogeneo), etichette);
+            }
+        }
+    }
+
+    #[test]
+    fn test_blocchi() {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("r"), 1);
+        piano.colora(1, 1, String::from("g"), 2);
+        piano.colora(5, 5, String::from("r"), 4);
+        assert_eq!(piano.stampa_blocchi(false), "(\n0 0 2 3\n5 5 1 4\n)");
+        assert_eq!(
+            piano.stampa_blocchi(true),
+            "(\n0 0 1 1\n1 1 1 2\n5 5 1 4\n)"
+        );
+        assert_eq!(Piano::new().stampa_blocchi(false), "(\n)");
+
+        let mut output = Vec::new();
+        for riga in ["thread 4", "N omogenei", "T 1", "blocchi"] {
+            esegui(&mut piano, riga, Formato::Testo, &mut |s| output.push(s)).unwrap();
+        }
+        assert_eq!(
+            output,
+            vec![piano.stampa_blocchi(true), piano.stampa_blocchi(false)]
+        );
+        for riga in ["T 0", "T", "N 1", "N omogenei 1"] {
+            assert!(esegui(&mut piano, riga, Formato::Testo, &mut |_| ()).is_err());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "il numero di thread deve essere maggiore di 0")]
+    fn test_imposta_thread_zero() {
+        Piano::new().imposta_thread(0);
+    }
+}
+
+/// Test per l'indice spaziale e le interrogazioni sui rettangoli
+mod spaziale {
+    #[cfg(test)]
+    use crate::*;
+
+    use crate::generatore::Casuale;
+
+    /// Riepiloga il rettangolo controllando ogni piastrella accesa del piano
+    fn riepiloga_ingenuo(piano: &Piano, (x1, y1): (i32, i32), (x2, y2): (i32, i32)) -> String {
+        let mut piastrelle = 0;
+        let mut intensita = 0;
+        let mut colori: HashMap<String, usize> = HashMap::new();
+        for (Piastrella { x, y }, colorazione) in piano.colorazioni() {
+            if (x1.min(x2)..=x1.max(x2)).contains(&x) && (y1.min(y2)..=y1.max(y2)).contains(&y) {
+                piastrelle += 1;
+                intensita += colorazione.intensita as u64;
+                *colori.entry(colorazione.colore).or_default() += 1;
+            }
+        }
+        let mut colori: Vec<(String, usize)> = colori.into_iter().collect();
+        colori.sort();
+        format!("{piastrelle} {intensita} {colori:?}")
+    }
+
+    fn riepiloga(piano: &Piano, (x1, y1): (i32, i32), (x2, y2): (i32, i32)) -> String {
+        format!(
+            "{} {} {:?}",
+            piano.conta_rettangolo(x1, y1, x2, y2),
+            piano.intensita_rettangolo(x1, y1, x2, y2),
+            piano
+                .istogramma_rettangolo(x1, y1, x2, y2)
+                .into_iter()
+                .map(|(colore, n)| (String::from(colore), n))
+                .collect::<Vec<_>>()
+        )
+    }
+
+    #[test]
+    fn test_rettangoli() {
+        let mut piano = Piano::new();
+        piano.colora(0, 0, String::from("r"), 2);
+        piano.colora(63, 63, String::from("g"), 3);
+        piano.colora(64, 64, String::from("r"), 4);
+        piano.colora(-1, -200, String::from("b"), 5);
+
+        assert_eq!(piano.conta_rettangolo(0, 0, 64, 64), 3);
+        assert_eq!(piano.conta_rettangolo(64, 64, 0, 0), 3);
+        assert_eq!(piano.intensita_rettangolo(1, 1, 64, 64), 7);
+        assert_eq!(
+            piano.istogramma_rettangolo(0, 0, 63, 63),
+            vec![("g", 1), ("r", 1)]
+        );
+        assert_eq!(piano.conta_rettangolo(1, 1, 62, 62), 0);
+        assert_eq!(
+            piano.conta_rettangolo(i32::MIN, i32::MIN, i32::MAX, i32::MAX),
+            4
+        );
+
+        piano.spegni(0, 0);
+        piano.colora(64, 64, String::from("g"), 1);
+        assert_eq!(piano.istogramma_rettangolo(0, 0, 64, 64), vec![("g", 2)]);
+        piano.spegni_rettangolo(-1000, -1000, 1000, 0);
+        assert_eq!(
+            piano.intensita_rettangolo(i32::MIN, i32::MIN, i32::MAX, i32::MAX),
+            4
+        );
+    }
+
+    #[test]
+    fn test_rettangoli_casuale() {
+        // operazioni casuali che modificano le piastrelle, confrontate con una visita di tutto
+        // il piano; con la scala grande le piastrelle sono sparse su più livelli dell'indice
+        for scala in [1, 100_000] {
+            rettangoli_casuali(scala);
+        }
+    }
+
+    fn rettangoli_casuali(scala: i64) {
+        let mut piano = Piano::new();
+        let mut casuale = Casuale::new(5);
+        let colori = ["r", "g", "b", "y"];
+        piano.regola(String::from("g 2 r"));
+        piano.regola(String::from("b 3 g"));
+        piano.regola(String::from("y 1 b 1 r"));
+        let punto = |casuale: &mut Casuale| {
+            (
+                (casuale.intervallo(-150, 150) * scala + casuale.intervallo(0, scala - 1)) as i32,
+                (casuale.intervallo(-150, 150) * scala + casuale.intervallo(0, scala - 1)) as i32,
+            )
+        };
+
+        for passo in 0..3000 {
+            let (x, y) = punto(&mut casuale);
+            let colore = String::from(*casuale.scegli(&colori));
+            let intensita = casuale.intervallo(1, 20) as u32;
+            match casuale.intervallo(0, 9) {
+                0..=4 => piano.colora(x, y, colore, intensita),
+                5 => piano.spegni(x, y),
+                6 => piano.colora_rettangolo(x, y, x + 9, y + 4, colore, intensita),
+                7 => piano.spegni_rettangolo(x, y, x + 3, y + 6),
+                8 => piano.propaga(x, y),
+                _ => piano.propaga_blocco(x, y),
+            }
+
+            if passo % 200 == 0 {
+                for _ in 0..10 {
+                    let (vertice, opposto) = (punto(&mut casuale), punto(&mut casuale));
+                    assert_eq!(
+                        riepiloga(&piano, vertice, opposto),
+                        riepiloga_ingenuo(&piano, vertice, opposto)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_comandi() {
+        let mut piano = Piano::new();
+        let mut output = Vec::new();
+        for riga in [
+            "C 0 0 r 2",
+            "C 1 1 g 3",
+            "C 5 5 r 1",
+            "conta 0 0 5 5",
+            "w 5 5 0 0",
+            "istogramma 0 0 5 5",
+            "g 10 10 20 20",
+        ] {
+            esegui(&mut piano, riga, Formato::Testo, &mut |s| output.push(s)).unwrap();
+        }
+        for riga in ["g 0 0 1 1", "n 0 0 1 1", "w 0 0 1 1"] {
+            esegui(&mut piano, riga, Formato::Json, &mut |s| output.push(s)).unwrap();
+        }
+        assert_eq!(
+            output,
+            vec![
+                "3",
+                "6",
+                "g 1 r 2",
+                "nessuno",
+                "{\"comando\":\"g\",\"argomenti\":[\"0\",\"0\",\"1\",\"1\"],\"risultato\":{\"g\":1,\"r\":1}}",
+                "{\"comando\":\"n\",\"argomenti\":[\"0\",\"0\",\"1\",\"1\"],\"risultato\":2}",
+                "{\"comando\":\"w\",\"argomenti\":[\"0\",\"0\",\"1\",\"1\"],\"risultato\":5}"
+            ]
+        );
+
+        for riga in ["n 0 0 1", "w 0 0 1 a", "g"] {
+            assert!(esegui(&mut piano, riga, Formato::Testo, &mut |_| ()).is_err());
+        }
+        let mut interprete = script::Interprete::new(Path::new("."));
+        for riga in [
+            "expect n 0 0 5 5 = 3",
+            "expect g 0 0 0 0 = r 1",
+            "expect g 9 9 9 9 = nessuno",
+        ] {
+            interprete.esegui(&mut piano, riga, &mut |_| ()).unwrap();
+        }
+    }
+}